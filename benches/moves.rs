@@ -0,0 +1,42 @@
+use std::time::Instant;
+
+use snake::board::Board;
+use snake::player::Player;
+use snake::spawn::Spawn;
+use snake::utils::{Vec2D, Rectangle, Direction};
+use snake::utils::constants::INITIAL_LENGTH;
+
+const MOVES: usize = 100_000;
+
+// Grows a snake to `length` along a straight corridor, then times `MOVES` moves.
+// The time per move should not depend on the length.
+fn bench_moves(length: usize) {
+    let playing_area = Rectangle::new(0, 0, (length + MOVES + 16) as i32, 5);
+    let mut board = Board::new(&playing_area);
+    let mut player = Player::new(0);
+    player.reset(&mut board, &Spawn::new(Vec2D::new(INITIAL_LENGTH as i32 - 1, 2), Direction::Right, INITIAL_LENGTH));
+
+    let initial_length = player.body.len();
+    player.grow((length - initial_length) as i32);
+    for _ in initial_length..length {
+        player.r#move(&mut board);
+    }
+
+    let start = Instant::now();
+    for _ in 0..MOVES {
+        player.r#move(&mut board);
+    }
+    let elapsed = start.elapsed();
+
+    println!(
+        "length {:>9}: {:>8.1} ns/move",
+        player.body.len(),
+        elapsed.as_nanos() as f64 / MOVES as f64
+    );
+}
+
+fn main() {
+    for &length in [10, 1_000, 10_000, 100_000].iter() {
+        bench_moves(length);
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Turn {
+    Left,
+    Right,
+}
+
+// What the achievements hear about from the game. Levels have no end yet, so there is no
+// event for completing one until they get a goal of their own.
+pub enum AchievementEvent {
+    Ate,
+    // Sent after every tick the snake survived.
+    Moved { ticks: u64, length: usize, cell_count: usize },
+    Turned(Turn),
+    Died,
+}
+
+// Goals are reached within a single run. Durations are in ticks so that they do not depend
+// on the game speed, 4 ticks are a second at normal speed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Goal {
+    Eat { count: u32 },
+    EatWithoutTurning { count: u32, turn: Turn },
+    FillBoard { percent: u32 },
+    Survive { ticks: u64 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Achievement {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub goal: Goal,
+}
+
+#[derive(Default)]
+struct RunProgress {
+    food: u32,
+    food_since_left: u32,
+    food_since_right: u32,
+}
+
+// Achievement definitions, loaded from a JSON list, and the ids of the unlocked ones.
+#[derive(Default)]
+pub struct Achievements {
+    definitions: Vec<Achievement>,
+    unlocked: BTreeSet<String>,
+    run: RunProgress,
+}
+
+impl Achievements {
+    pub fn new<R: Read>(definitions: R) -> io::Result<Achievements> {
+        Ok(Achievements {
+            definitions: serde_json::from_reader(definitions)?,
+            ..Achievements::default()
+        })
+    }
+
+    // A missing file means nothing was unlocked yet.
+    pub fn load_unlocked(&mut self, path: &Path) -> io::Result<()> {
+        self.unlocked = match File::open(path) {
+            Ok(file) => serde_json::from_reader(file)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeSet::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(())
+    }
+
+    pub fn save_unlocked(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        serde_json::to_writer_pretty(File::create(path)?, &self.unlocked)?;
+        Ok(())
+    }
+
+    // Unlocked and total number of achievements.
+    pub fn progress(&self) -> (usize, usize) {
+        let unlocked = self.definitions.iter()
+            .filter(|achievement| self.unlocked.contains(&achievement.id))
+            .count();
+        (unlocked, self.definitions.len())
+    }
+
+    // Returns the achievements unlocked by this event.
+    pub fn handle(&mut self, event: &AchievementEvent) -> Vec<Achievement> {
+        match *event {
+            AchievementEvent::Ate => {
+                self.run.food += 1;
+                self.run.food_since_left += 1;
+                self.run.food_since_right += 1;
+            }
+            AchievementEvent::Turned(Turn::Left) => self.run.food_since_left = 0,
+            AchievementEvent::Turned(Turn::Right) => self.run.food_since_right = 0,
+            AchievementEvent::Moved { .. } => (),
+            AchievementEvent::Died => {
+                self.run = RunProgress::default();
+                return Vec::new();
+            }
+        }
+
+        let run = &self.run;
+        let reached: Vec<Achievement> = self.definitions.iter()
+            .filter(|achievement| !self.unlocked.contains(&achievement.id))
+            .filter(|achievement| match (&achievement.goal, event) {
+                (Goal::Eat { count }, AchievementEvent::Ate) => run.food >= *count,
+                (Goal::EatWithoutTurning { count, turn: Turn::Left }, AchievementEvent::Ate) => run.food_since_left >= *count,
+                (Goal::EatWithoutTurning { count, turn: Turn::Right }, AchievementEvent::Ate) => run.food_since_right >= *count,
+                (Goal::FillBoard { percent }, AchievementEvent::Moved { length, cell_count, .. }) => {
+                    *length * 100 >= *cell_count * *percent as usize
+                }
+                (Goal::Survive { ticks: goal }, AchievementEvent::Moved { ticks, .. }) => ticks >= goal,
+                _ => false,
+            })
+            .cloned()
+            .collect();
+        for achievement in reached.iter() {
+            self.unlocked.insert(achievement.id.clone());
+        }
+        reached
+    }
+
+    // Forgets the progress of the current run, unlocks stay.
+    pub fn start_run(&mut self) {
+        self.run = RunProgress::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn achievements(goal: Goal) -> Achievements {
+        let achievement = Achievement {
+            id: String::from("goal"),
+            name: String::from("Goal"),
+            description: String::new(),
+            goal,
+        };
+        Achievements { definitions: vec![achievement], ..Achievements::default() }
+    }
+
+    fn moved(ticks: u64, length: usize) -> AchievementEvent {
+        AchievementEvent::Moved { ticks, length, cell_count: 100 }
+    }
+
+    fn unlocked_ids(achievements: &mut Achievements, event: &AchievementEvent) -> Vec<String> {
+        achievements.handle(event).into_iter().map(|achievement| achievement.id).collect()
+    }
+
+    #[test]
+    fn loads_the_bundled_definitions() {
+        let file = File::open("resources/achievements.json").unwrap();
+        let achievements = Achievements::new(file).unwrap();
+        assert_eq!(achievements.progress(), (0, 5));
+    }
+
+    #[test]
+    fn eat_counts_food_in_one_run() {
+        let mut achievements = achievements(Goal::Eat { count: 2 });
+        assert!(achievements.handle(&AchievementEvent::Ate).is_empty());
+        assert_eq!(unlocked_ids(&mut achievements, &AchievementEvent::Ate), vec!["goal"]);
+        assert_eq!(achievements.progress(), (1, 1));
+        // Unlocks only happen once.
+        assert!(achievements.handle(&AchievementEvent::Ate).is_empty());
+    }
+
+    #[test]
+    fn eat_without_turning_starts_over_on_that_turn() {
+        let mut achievements = achievements(Goal::EatWithoutTurning { count: 2, turn: Turn::Left });
+        achievements.handle(&AchievementEvent::Ate);
+        achievements.handle(&AchievementEvent::Turned(Turn::Left));
+        assert!(achievements.handle(&AchievementEvent::Ate).is_empty());
+        achievements.handle(&AchievementEvent::Turned(Turn::Right));
+        assert_eq!(unlocked_ids(&mut achievements, &AchievementEvent::Ate), vec!["goal"]);
+    }
+
+    #[test]
+    fn fill_board_compares_the_length_with_the_board() {
+        let mut achievements = achievements(Goal::FillBoard { percent: 50 });
+        assert!(achievements.handle(&moved(1, 49)).is_empty());
+        assert_eq!(unlocked_ids(&mut achievements, &moved(2, 50)), vec!["goal"]);
+    }
+
+    #[test]
+    fn survive_counts_ticks() {
+        let mut achievements = achievements(Goal::Survive { ticks: 1200 });
+        assert!(achievements.handle(&moved(1199, 5)).is_empty());
+        assert_eq!(unlocked_ids(&mut achievements, &moved(1200, 5)), vec!["goal"]);
+    }
+
+    #[test]
+    fn progress_is_lost_on_death_and_new_runs() {
+        let mut achievements = achievements(Goal::Eat { count: 2 });
+        achievements.handle(&AchievementEvent::Ate);
+        assert!(achievements.handle(&AchievementEvent::Died).is_empty());
+        assert!(achievements.handle(&AchievementEvent::Ate).is_empty());
+        achievements.start_run();
+        assert!(achievements.handle(&AchievementEvent::Ate).is_empty());
+        assert_eq!(unlocked_ids(&mut achievements, &AchievementEvent::Ate), vec!["goal"]);
+    }
+}
@@ -0,0 +1,283 @@
+use std::collections::VecDeque;
+
+use serde::{Serialize, Deserialize};
+
+use crate::utils::{Vec2D, Rectangle, Direction};
+
+pub type SnakeId = u8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FoodKind {
+    Regular,
+}
+
+// Snake cells store the sequence number the segment got when it was the head, which stays valid
+// while the snake moves, see `Player::segment_index`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Cell {
+    Empty,
+    Wall,
+    Snake(SnakeId, u32),
+    Food(FoodKind),
+    Hazard,
+    // Snakes never stay on a portal, they come out of its partner, see `Board::step`.
+    Portal,
+}
+
+impl Cell {
+    pub fn is_free(&self) -> bool {
+        match self {
+            Cell::Empty | Cell::Food(_) => true,
+            Cell::Wall | Cell::Snake(_, _) | Cell::Hazard | Cell::Portal => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Board {
+    area: Rectangle,
+    cells: Vec<Cell>,
+    free_cell_count: usize,
+    portals: Vec<(Vec2D, Vec2D)>,
+}
+
+impl Board {
+    pub fn new(area: &Rectangle) -> Board {
+        let cell_count = (area.width() * area.height()) as usize;
+        Board {
+            area: area.clone(),
+            cells: vec![Cell::Empty; cell_count],
+            free_cell_count: cell_count,
+            portals: Vec::new(),
+        }
+    }
+
+    pub fn area(&self) -> &Rectangle {
+        &self.area
+    }
+
+    // Portals are part of the layout and stay.
+    pub fn clear(&mut self) {
+        for cell in self.cells.iter_mut() {
+            *cell = Cell::Empty;
+        }
+        self.free_cell_count = self.cells.len();
+        for (first, second) in self.portals.clone() {
+            self.set(&first, Cell::Portal);
+            self.set(&second, Cell::Portal);
+        }
+    }
+
+    pub fn add_portal(&mut self, first: Vec2D, second: Vec2D) {
+        self.set(&first, Cell::Portal);
+        self.set(&second, Cell::Portal);
+        self.portals.push((first, second));
+    }
+
+    pub fn portals(&self) -> &[(Vec2D, Vec2D)] {
+        &self.portals
+    }
+
+    // Cell reached by leaving `position` toward `direction`. Entering a portal leads to the cell
+    // past its partner, in the same direction.
+    pub fn step(&self, position: &Vec2D, direction: Direction) -> Vec2D {
+        let next = *position + direction.to_vec();
+        let partner = self.portals.iter().find_map(|&(first, second)| {
+            if first == next {
+                Some(second)
+            } else if second == next {
+                Some(first)
+            } else {
+                None
+            }
+        });
+        match partner {
+            Some(partner) => partner + direction.to_vec(),
+            None => next,
+        }
+    }
+
+    // Positions outside of the board are `None`, callers usually treat them as walls.
+    pub fn get(&self, position: &Vec2D) -> Option<Cell> {
+        self.index(position).map(|index| self.cells[index])
+    }
+
+    pub fn set(&mut self, position: &Vec2D, cell: Cell) {
+        if let Some(index) = self.index(position) {
+            match (self.cells[index].is_free(), cell.is_free()) {
+                (true, false) => self.free_cell_count -= 1,
+                (false, true) => self.free_cell_count += 1,
+                _ => (),
+            }
+            self.cells[index] = cell;
+        }
+    }
+
+    pub fn is_free(&self, position: &Vec2D) -> bool {
+        self.get(position).is_some_and(|cell| cell.is_free())
+    }
+
+    // Free cells are the ones a snake can enter: empty or food.
+    pub fn free_cell_count(&self) -> usize {
+        self.free_cell_count
+    }
+
+    pub fn free_cells(&self) -> impl Iterator<Item = Vec2D> + '_ {
+        self.cells.iter()
+            .enumerate()
+            .filter(|(_, cell)| cell.is_free())
+            .map(move |(index, _)| self.position(index))
+    }
+
+    // Free cells without food, where new things can be put.
+    pub fn empty_cells(&self) -> impl Iterator<Item = Vec2D> + '_ {
+        self.cells.iter()
+            .enumerate()
+            .filter(|(_, cell)| **cell == Cell::Empty)
+            .map(move |(index, _)| self.position(index))
+    }
+
+    pub fn neighbors(&self, position: &Vec2D) -> impl Iterator<Item = Vec2D> + '_ {
+        let position = *position;
+        Direction::ALL.iter()
+            .map(move |direction| position + direction.to_vec())
+            .filter(move |neighbor| self.area.contains(neighbor))
+    }
+
+    // Number of free cells reachable from `from` without going through walls or snakes, moving
+    // like a snake does, through portals. `from` itself is not counted.
+    pub fn reachable_area(&self, from: &Vec2D) -> usize {
+        let mut visited = vec![false; self.cells.len()];
+        let mut queue = VecDeque::new();
+        let mut count = 0;
+
+        if let Some(index) = self.index(from) {
+            visited[index] = true;
+            queue.push_back(*from);
+        }
+
+        while let Some(position) = queue.pop_front() {
+            for direction in Direction::ALL.iter() {
+                let neighbor = self.step(&position, *direction);
+                let index = match self.index(&neighbor) {
+                    Some(index) => index,
+                    None => continue,
+                };
+                if !visited[index] && self.cells[index].is_free() {
+                    visited[index] = true;
+                    count += 1;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        count
+    }
+
+    fn index(&self, position: &Vec2D) -> Option<usize> {
+        if !self.area.contains(position) {
+            return None;
+        }
+        let x = (position.x - self.area.min.x) as usize;
+        let y = (position.y - self.area.min.y) as usize;
+        Some(y * self.area.width() as usize + x)
+    }
+
+    fn position(&self, index: usize) -> Vec2D {
+        let width = self.area.width() as usize;
+        Vec2D::new(
+            self.area.min.x + (index % width) as i32,
+            self.area.min.y + (index / width) as i32
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board() -> Board {
+        Board::new(&Rectangle::new(0, 0, 4, 4))
+    }
+
+    #[test]
+    fn free_cell_count_follows_every_change() {
+        let mut board = board();
+        assert_eq!(board.free_cell_count(), 25);
+        board.set(&Vec2D::new(1, 1), Cell::Wall);
+        board.set(&Vec2D::new(1, 1), Cell::Hazard);
+        board.set(&Vec2D::new(2, 2), Cell::Food(FoodKind::Regular));
+        board.set(&Vec2D::new(3, 3), Cell::Snake(0, 0));
+        assert_eq!(board.free_cell_count(), 23);
+        board.set(&Vec2D::new(3, 3), Cell::Empty);
+        board.set(&Vec2D::new(2, 2), Cell::Empty);
+        // Outside of the board nothing changes.
+        board.set(&Vec2D::new(5, 0), Cell::Wall);
+        assert_eq!(board.free_cell_count(), 24);
+        assert_eq!(board.free_cells().count(), 24);
+        assert!(!board.free_cells().any(|position| position == Vec2D::new(1, 1)));
+    }
+
+    #[test]
+    fn empty_cells_leave_food_out() {
+        let mut board = board();
+        board.set(&Vec2D::new(2, 2), Cell::Food(FoodKind::Regular));
+        assert_eq!(board.free_cells().count(), 25);
+        assert_eq!(board.empty_cells().count(), 24);
+    }
+
+    #[test]
+    fn clear_keeps_portals() {
+        let mut board = board();
+        board.add_portal(Vec2D::new(0, 0), Vec2D::new(4, 4));
+        board.set(&Vec2D::new(2, 2), Cell::Wall);
+        assert_eq!(board.free_cell_count(), 22);
+        board.clear();
+        assert_eq!(board.free_cell_count(), 23);
+        assert_eq!(board.get(&Vec2D::new(4, 4)), Some(Cell::Portal));
+        assert_eq!(board.get(&Vec2D::new(2, 2)), Some(Cell::Empty));
+    }
+
+    #[test]
+    fn neighbors_stay_on_the_board() {
+        let board = board();
+        let mut corner: Vec<Vec2D> = board.neighbors(&Vec2D::new(0, 0)).collect();
+        corner.sort_by_key(|position| (position.x, position.y));
+        assert_eq!(corner, vec![Vec2D::new(0, 1), Vec2D::new(1, 0)]);
+        assert_eq!(board.neighbors(&Vec2D::new(4, 2)).count(), 3);
+        assert_eq!(board.neighbors(&Vec2D::new(2, 2)).count(), 4);
+        assert_eq!(board.get(&Vec2D::new(-1, 0)), None);
+    }
+
+    #[test]
+    fn step_goes_through_portals() {
+        let mut board = board();
+        board.add_portal(Vec2D::new(1, 0), Vec2D::new(3, 3));
+        assert_eq!(board.step(&Vec2D::new(0, 0), Direction::Right), Vec2D::new(4, 3));
+        assert_eq!(board.step(&Vec2D::new(3, 2), Direction::Down), Vec2D::new(1, 1));
+        assert_eq!(board.step(&Vec2D::new(0, 0), Direction::Down), Vec2D::new(0, 1));
+    }
+
+    #[test]
+    fn reachable_area_stops_at_walls() {
+        let mut board = board();
+        assert_eq!(board.reachable_area(&Vec2D::new(0, 0)), 24);
+        // A wall down the middle column leaves two columns on the left.
+        for y in 0..5 {
+            board.set(&Vec2D::new(2, y), Cell::Wall);
+        }
+        assert_eq!(board.reachable_area(&Vec2D::new(0, 0)), 9);
+        assert_eq!(board.reachable_area(&Vec2D::new(4, 4)), 9);
+        assert_eq!(board.reachable_area(&Vec2D::new(7, 7)), 0);
+    }
+
+    #[test]
+    fn reachable_area_follows_portals() {
+        let mut board = board();
+        for y in 0..5 {
+            board.set(&Vec2D::new(2, y), Cell::Wall);
+        }
+        board.add_portal(Vec2D::new(1, 2), Vec2D::new(4, 2));
+        // Both sides have 9 free cells once the portals are in, the start is not counted.
+        assert_eq!(board.reachable_area(&Vec2D::new(0, 0)), 8 + 9);
+    }
+}
@@ -0,0 +1,79 @@
+use ggez::graphics;
+use ggez::mint::Point2;
+use ggez::mint::ColumnMatrix4;
+
+use crate::utils::Rectangle;
+use crate::utils::constants::*;
+
+pub struct Viewport {
+    board_width: f32,
+    board_height: f32,
+    margin: f32,
+    window_width: f32,
+    window_height: f32,
+    scale: f32,
+    offset: Point2<f32>,
+}
+
+impl Viewport {
+    // The margin, in board pixels, is kept visible on every side of the board.
+    pub fn new(playing_area: &Rectangle, margin: f32) -> Viewport {
+        let board_width = (playing_area.width() * CELL_SIZE) as f32;
+        let board_height = (playing_area.height() * CELL_SIZE) as f32;
+        let mut viewport = Viewport {
+            board_width,
+            board_height,
+            margin,
+            window_width: board_width,
+            window_height: board_height,
+            scale: 1.0,
+            offset: Point2 { x: 0.0, y: 0.0 },
+        };
+        let (width, height) = viewport.framed_size();
+        viewport.resize(width, height);
+        viewport
+    }
+
+    pub fn resize(&mut self, window_width: f32, window_height: f32) {
+        let (framed_width, framed_height) = self.framed_size();
+        self.window_width = window_width;
+        self.window_height = window_height;
+        self.scale = (window_width / framed_width).min(window_height / framed_height);
+        self.offset = Point2 {
+            x: (window_width - self.board_width * self.scale) / 2.0,
+            y: (window_height - self.board_height * self.scale) / 2.0,
+        };
+    }
+
+    // Size of the board and its margin, in board pixels.
+    pub fn framed_size(&self) -> (f32, f32) {
+        (self.board_width + 2.0 * self.margin, self.board_height + 2.0 * self.margin)
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    pub fn window_size(&self) -> (f32, f32) {
+        (self.window_width, self.window_height)
+    }
+
+    pub fn screen_rect(&self) -> graphics::Rect {
+        graphics::Rect::new(0.0, 0.0, self.window_width, self.window_height)
+    }
+
+    pub fn board_rect(&self) -> graphics::Rect {
+        graphics::Rect::new(0.0, 0.0, self.board_width, self.board_height)
+    }
+
+    // Maps board pixels (cells * CELL_SIZE) to window pixels, the shake offset is given in board pixels.
+    pub fn board_transform(&self, shake: Point2<f32>) -> ColumnMatrix4<f32> {
+        graphics::DrawParam::new()
+            .dest(Point2 {
+                x: self.offset.x + shake.x * self.scale,
+                y: self.offset.y + shake.y * self.scale,
+            })
+            .scale([self.scale, self.scale])
+            .to_matrix()
+    }
+}
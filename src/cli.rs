@@ -0,0 +1,211 @@
+use std::path::PathBuf;
+
+use log::LevelFilter;
+
+use crate::effects::EffectsConf;
+use crate::food::{FoodConf, FoodPolicy};
+use crate::hazard::HazardConf;
+use crate::level::portal_by_id;
+use crate::logging;
+use crate::mode::{GameMode, Variants};
+use crate::spawn::{SpawnConf, Placement};
+use crate::utils::Vec2D;
+use crate::utils::constants::{MIN_SPEED, MAX_SPEED};
+
+pub const USAGE: &str = "\
+Usage: snake [OPTIONS]
+
+Options:
+    --tui                               Play in the terminal instead of a window
+    --replay <FILE>                     With --tui, watch a replay instead of playing
+    --export-replay <FILE> <OUTPUT>     Render a replay to a GIF, or to a directory of PNG frames
+    --theme <NAME>                      Board theme: default, grass or blueprint
+    --background <RESOURCE>             Background image of the board, e.g. /background.png
+    --size <WIDTH>x<HEIGHT>             Size of the board in cells, 10x10 by default
+    --length <N>                        Length of the snake when it spawns, 7 by default
+    --random-spawn                      Spawn the snake at a random place instead of the center
+    --food <N>                          Number of food items on the board at once, 1 by default
+    --food-policy <POLICY>              Where food appears: uniform, away[:DISTANCE], avoid-edges[:MARGIN]
+                                        or points:X,Y+X,Y... to use the given cells in turn
+    --food-lifetime <TICKS>             Food disappears when not eaten in time
+    --food-respawn <TICKS>              Delay before eaten or expired food comes back, 0 by default
+    --fleeing-food <TICKS>              Food steps away from the head every TICKS ticks
+    --bouncers <N>                      Number of bouncing blocks that kill on contact
+    --bounce-every <TICKS>              Bouncers move every TICKS ticks, 2 by default
+    --temporary-walls <N>               Number of walls that come and go
+    --wall-timing <UP>,<DOWN>           Ticks the temporary walls stay up and down, 20,20 by default
+    --portal <X>,<Y>:<X>,<Y>            Adds a pair of portals, can be repeated
+    --level <FILE>                      Board layout file, it sets the board size, portals and food spawn points
+    --mode <MODE>                       Game mode: classic, time-attack[:SECONDS], survival[:TICKS] or zen
+    --tail-cutting                      Biting the body cuts the tail off, for a score penalty, instead of dying
+    --speed <MULTIPLIER>                Simulation speed, from 0.25 to 8, also changed in game with + and -
+    --no-particles                      Turns off the particle bursts on eating and dying
+    --no-screen-shake                   Turns off the screen shake
+    --no-death-animation                Shows the game over screen right away, without the death animation
+    --log-level <LEVEL>                 Messages written to stderr: off, error, warn, info, debug or trace, warn by default";
+
+pub enum Command {
+    Play,
+    Tui,
+    WatchReplay(PathBuf),
+    ExportReplay(PathBuf, PathBuf),
+}
+
+pub struct Options {
+    pub command: Command,
+    pub theme: Option<String>,
+    pub background: Option<String>,
+    pub size: (u32, u32),
+    pub spawn: SpawnConf,
+    pub food: FoodConf,
+    pub hazards: HazardConf,
+    pub portals: Vec<(Vec2D, Vec2D)>,
+    pub level: Option<PathBuf>,
+    pub speed: f32,
+    pub mode: GameMode,
+    pub variants: Variants,
+    pub effects: EffectsConf,
+    pub log_level: LevelFilter,
+}
+
+impl Options {
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+        let mut tui = false;
+        let mut replay = None;
+        let mut export = None;
+        let mut theme = None;
+        let mut background = None;
+        let mut size = (10, 10);
+        let mut spawn = SpawnConf::default();
+        let mut food = FoodConf::default();
+        let mut hazards = HazardConf::default();
+        let mut portals = Vec::new();
+        let mut level = None;
+        let mut speed = 1.0;
+        let mut mode = GameMode::default();
+        let mut variants = Variants::default();
+        let mut effects = EffectsConf::default();
+        let mut log_level = LevelFilter::Warn;
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("Missing value for {}", arg));
+            match arg.as_str() {
+                "--tui" => tui = true,
+                "--replay" => replay = Some(PathBuf::from(value()?)),
+                "--export-replay" => export = Some((PathBuf::from(value()?), PathBuf::from(value()?))),
+                "--theme" => theme = Some(value()?),
+                "--background" => background = Some(value()?),
+                "--size" => size = parse_size(&value()?)?,
+                "--length" => {
+                    let length = value()?;
+                    spawn.length = length.parse().map_err(|_| format!("Invalid snake length: {}", length))?;
+                }
+                "--random-spawn" => spawn.placement = Placement::Random,
+                "--food" => {
+                    let value = value()?;
+                    food.count = value.parse().map_err(|_| format!("Invalid food count: {}", value))?;
+                }
+                "--food-policy" => {
+                    let value = value()?;
+                    food.policy = FoodPolicy::by_id(&value).ok_or(format!("Unknown food policy: {}", value))?;
+                }
+                "--food-lifetime" => {
+                    let value = value()?;
+                    food.lifetime = Some(value.parse().map_err(|_| format!("Invalid food lifetime: {}", value))?);
+                }
+                "--food-respawn" => {
+                    let value = value()?;
+                    food.respawn_delay = value.parse().map_err(|_| format!("Invalid food respawn delay: {}", value))?;
+                }
+                "--fleeing-food" => {
+                    let value = value()?;
+                    food.flee_every = Some(value.parse().map_err(|_| format!("Invalid fleeing food period: {}", value))?);
+                }
+                "--bouncers" => {
+                    let value = value()?;
+                    hazards.bouncers = value.parse().map_err(|_| format!("Invalid bouncer count: {}", value))?;
+                }
+                "--bounce-every" => {
+                    let value = value()?;
+                    hazards.bounce_every = value.parse().map_err(|_| format!("Invalid bouncer period: {}", value))?;
+                }
+                "--temporary-walls" => {
+                    let value = value()?;
+                    hazards.temporary_walls = value.parse().map_err(|_| format!("Invalid wall count: {}", value))?;
+                }
+                "--wall-timing" => {
+                    let value = value()?;
+                    let invalid = || format!("Invalid wall timing: {}", value);
+                    let mut timing = value.split(',');
+                    hazards.wall_up = timing.next().and_then(|up| up.parse().ok()).ok_or_else(invalid)?;
+                    hazards.wall_down = timing.next().and_then(|down| down.parse().ok()).ok_or_else(invalid)?;
+                    if timing.next().is_some() {
+                        return Err(invalid());
+                    }
+                }
+                "--portal" => {
+                    let value = value()?;
+                    portals.push(portal_by_id(&value).ok_or(format!("Invalid portal: {}", value))?);
+                }
+                "--level" => level = Some(PathBuf::from(value()?)),
+                "--mode" => {
+                    let value = value()?;
+                    mode = GameMode::by_id(&value).ok_or(format!("Unknown game mode: {}", value))?;
+                }
+                "--tail-cutting" => variants.tail_cutting = true,
+                "--speed" => {
+                    let value = value()?;
+                    speed = value.parse().map_err(|_| format!("Invalid speed: {}", value))?;
+                    if !(MIN_SPEED..=MAX_SPEED).contains(&speed) {
+                        return Err(format!("Speed must be between {} and {}, got {}", MIN_SPEED, MAX_SPEED, value));
+                    }
+                }
+                "--no-particles" => effects.particles = false,
+                "--no-screen-shake" => effects.screen_shake = false,
+                "--no-death-animation" => effects.death_animation = false,
+                "--log-level" => {
+                    let value = value()?;
+                    log_level = logging::level_by_id(&value).ok_or(format!("Unknown log level: {}", value))?;
+                }
+                _ => return Err(format!("Unknown argument: {}", arg)),
+            }
+        }
+
+        let command = match (tui, replay, export) {
+            (_, None, Some((replay, output))) => Command::ExportReplay(replay, output),
+            (true, Some(replay), None) => Command::WatchReplay(replay),
+            (true, None, None) => Command::Tui,
+            (false, None, None) => Command::Play,
+            (false, Some(_), _) => return Err(String::from("--replay can only be used with --tui")),
+            (_, Some(_), Some(_)) => return Err(String::from("--replay and --export-replay are exclusive")),
+        };
+
+        Ok(Options {
+            command,
+            theme,
+            background,
+            size,
+            spawn,
+            food,
+            hazards,
+            portals,
+            level,
+            speed,
+            mode,
+            variants,
+            effects,
+            log_level,
+        })
+    }
+}
+
+fn parse_size(value: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("Invalid board size: {}", value);
+    let mut dimensions = value.split('x');
+    let width: u32 = dimensions.next().and_then(|width| width.parse().ok()).ok_or_else(invalid)?;
+    let height: u32 = dimensions.next().and_then(|height| height.parse().ok()).ok_or_else(invalid)?;
+    if dimensions.next().is_some() || width == 0 || height == 0 {
+        return Err(invalid());
+    }
+    Ok((width, height))
+}
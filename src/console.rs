@@ -0,0 +1,99 @@
+use crate::utils::Vec2D;
+
+pub const HELP: &str = "grow <N>, spawn food <X> <Y>, speed [MULTIPLIER], seed [SEED], pause, step [N]";
+
+pub enum ConsoleCommand {
+    Grow(i32),
+    SpawnFood(Vec2D),
+    Speed(Option<f32>),
+    Seed(Option<u64>),
+    Pause,
+    Step(u32),
+    Help,
+}
+
+impl ConsoleCommand {
+    pub fn parse(line: &str) -> Result<ConsoleCommand, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["grow", amount] => Ok(ConsoleCommand::Grow(parse_arg(amount)?)),
+            ["spawn", "food", x, y] => Ok(ConsoleCommand::SpawnFood(Vec2D::new(parse_arg(x)?, parse_arg(y)?))),
+            ["speed"] => Ok(ConsoleCommand::Speed(None)),
+            ["speed", speed] => Ok(ConsoleCommand::Speed(Some(parse_speed(speed)?))),
+            ["seed"] => Ok(ConsoleCommand::Seed(None)),
+            ["seed", seed] => Ok(ConsoleCommand::Seed(Some(parse_arg(seed)?))),
+            ["pause"] => Ok(ConsoleCommand::Pause),
+            ["step"] => Ok(ConsoleCommand::Step(1)),
+            ["step", count] => Ok(ConsoleCommand::Step(parse_arg(count)?)),
+            ["help"] => Ok(ConsoleCommand::Help),
+            _ => Err(format!("Unknown command: {}", line.trim())),
+        }
+    }
+}
+
+fn parse_arg<T: std::str::FromStr>(arg: &str) -> Result<T, String> {
+    arg.parse().map_err(|_| format!("Invalid argument: {}", arg))
+}
+
+// Out of range speeds are clamped by the timestep, only values that are not numbers are refused.
+fn parse_speed(arg: &str) -> Result<f32, String> {
+    let speed: f32 = parse_arg(arg)?;
+    if !speed.is_finite() {
+        return Err(format!("Invalid argument: {}", arg));
+    }
+    Ok(speed)
+}
+
+// Line editing state, commands are run by the frontend since they touch more than the game.
+#[derive(Default)]
+pub struct Console {
+    pub is_open: bool,
+    pub input: String,
+    pub output: String,
+}
+
+impl Console {
+    pub fn toggle(&mut self) {
+        self.is_open = !self.is_open;
+        self.input.clear();
+    }
+
+    pub fn push(&mut self, c: char) {
+        if !c.is_control() {
+            self.input.push(c);
+        }
+    }
+
+    pub fn pop(&mut self) {
+        self.input.pop();
+    }
+
+    pub fn submit(&mut self) -> Result<ConsoleCommand, String> {
+        let line = std::mem::take(&mut self.input);
+        ConsoleCommand::parse(&line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn speed_takes_finite_multipliers() {
+        assert!(matches!(ConsoleCommand::parse("speed"), Ok(ConsoleCommand::Speed(None))));
+        assert!(matches!(ConsoleCommand::parse("speed 2"), Ok(ConsoleCommand::Speed(Some(speed))) if speed == 2.0));
+        assert!(matches!(ConsoleCommand::parse("speed 100"), Ok(ConsoleCommand::Speed(Some(speed))) if speed == 100.0));
+        for arg in ["nan", "NaN", "inf", "-inf", "fast"].iter() {
+            assert_eq!(ConsoleCommand::parse(&format!("speed {}", arg)).err(), Some(format!("Invalid argument: {}", arg)));
+        }
+    }
+
+    #[test]
+    fn parses_commands_with_their_arguments() {
+        assert!(matches!(ConsoleCommand::parse("grow -2"), Ok(ConsoleCommand::Grow(-2))));
+        assert!(matches!(ConsoleCommand::parse(" spawn  food 3 4 "), Ok(ConsoleCommand::SpawnFood(position)) if position == Vec2D::new(3, 4)));
+        assert!(matches!(ConsoleCommand::parse("step"), Ok(ConsoleCommand::Step(1))));
+        assert!(matches!(ConsoleCommand::parse("seed 12"), Ok(ConsoleCommand::Seed(Some(12)))));
+        assert_eq!(ConsoleCommand::parse("jump").err(), Some(String::from("Unknown command: jump")));
+    }
+}
@@ -0,0 +1,133 @@
+use std::fs;
+use std::io::{self, Write};
+use std::panic::{self, PanicHookInfo};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, TryLockError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::game::Game;
+use crate::replay::Replay;
+use crate::utils::Direction;
+
+// What the panic hook knows about the run in progress: the game when the run started and
+// the inputs since. The state before the last tick is only rebuilt when a report is written.
+struct Snapshot {
+    config: String,
+    start: Option<Game>,
+    // Missing when the inputs that led to `start` are not known, e.g. after console changes.
+    replay: Option<Replay>,
+    inputs: Vec<Option<Direction>>,
+}
+
+static SNAPSHOT: Mutex<Snapshot> = Mutex::new(Snapshot {
+    config: String::new(),
+    start: None,
+    replay: None,
+    inputs: Vec::new(),
+});
+
+// Writes a report to `dir` on panic, on top of the usual message. `config` is anything
+// needed to start the game the same way, such as the command line.
+pub fn install(dir: PathBuf, config: String) {
+    snapshot().config = config;
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        match write_report(&dir, info) {
+            Ok(path) => log::error!("Crash report written to {}", path.display()),
+            Err(e) => log::error!("Failed to write the crash report: {}", e),
+        }
+    }));
+}
+
+// Called whenever the game changes other than by ticking. `replay` holds the inputs that led
+// to `game`, a resumed run does not start from its seed.
+pub fn start_run(game: &Game, replay: Option<&Replay>) {
+    let mut snapshot = snapshot();
+    snapshot.start = Some(game.clone());
+    snapshot.replay = replay.cloned();
+    snapshot.inputs.clear();
+}
+
+// Called before every tick with the input it is about to use.
+pub fn record(input: Option<Direction>) {
+    snapshot().inputs.push(input);
+}
+
+// A panic while recording leaves the snapshot poisoned, what was there is still worth reporting.
+fn snapshot() -> std::sync::MutexGuard<'static, Snapshot> {
+    SNAPSHOT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// Plays every input but the last one, that tick is the one that did not finish.
+fn state_before_last_tick(start: &Game, inputs: &[Option<Direction>]) -> Game {
+    let mut game = start.clone();
+    for input in inputs.iter().take(inputs.len().saturating_sub(1)) {
+        game.player.wanted_direction = *input;
+        game.tick();
+    }
+    game
+}
+
+fn write_report(dir: &Path, info: &PanicHookInfo) -> io::Result<PathBuf> {
+    // Blocking here would hang a thread that panicked while recording.
+    let snapshot = match SNAPSHOT.try_lock() {
+        Ok(snapshot) => snapshot,
+        Err(TryLockError::Poisoned(e)) => e.into_inner(),
+        Err(TryLockError::WouldBlock) => return Err(io::Error::other("The game snapshot is in use")),
+    };
+
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("crash-{}.txt", time));
+    let mut file = fs::File::create(&path)?;
+
+    writeln!(file, "{}", info)?;
+    writeln!(file, "\nConfig: {}", snapshot.config)?;
+    let game = snapshot.start.as_ref().map(|start| state_before_last_tick(start, &snapshot.inputs));
+    if let Some(game) = &game {
+        writeln!(file, "Seed: {}", game.seed())?;
+        writeln!(file, "Tick: {}", game.ticks())?;
+    }
+    // The two replay lines can be saved to a file and watched with --tui --replay.
+    if let Some(replay) = &snapshot.replay {
+        let mut replay = replay.clone();
+        replay.inputs.extend_from_slice(&snapshot.inputs);
+        writeln!(file, "\nReplay:")?;
+        replay.write(&mut file)?;
+    }
+    if let Some(game) = &game {
+        writeln!(file, "\nGame state:")?;
+        serde_json::to_writer_pretty(&mut file, game)?;
+        writeln!(file)?;
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::food::FoodConf;
+    use crate::hazard::HazardConf;
+    use crate::mode::GameMode;
+    use crate::spawn::SpawnConf;
+    use crate::utils::Rectangle;
+
+    #[test]
+    fn rebuilds_the_state_before_the_last_tick() {
+        let area = Rectangle::new(0, 0, 19, 19);
+        let start = Game::new(area, SpawnConf::default(), FoodConf::default(), HazardConf::default(), Vec::new(), GameMode::default(), 7)
+            .unwrap();
+        let inputs = [None, Some(Direction::Up), None, Some(Direction::Left)];
+
+        let mut expected = start.clone();
+        for input in inputs[..3].iter() {
+            expected.player.wanted_direction = *input;
+            expected.tick();
+        }
+        let game = state_before_last_tick(&start, &inputs);
+        assert_eq!(game.ticks(), 3);
+        assert_eq!(serde_json::to_string(&game).unwrap(), serde_json::to_string(&expected).unwrap());
+        assert_eq!(state_before_last_tick(&start, &[]).ticks(), 0);
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::VecDeque;
+
+use ggez::{graphics, Context, GameResult};
+use ggez::mint::Point2;
+
+use rand::Rng;
+
+use crate::utils::constants::*;
+
+pub struct EffectsConf {
+    pub particles: bool,
+    pub death_animation: bool,
+    pub screen_shake: bool,
+}
+
+impl Default for EffectsConf {
+    fn default() -> EffectsConf {
+        EffectsConf {
+            particles: true,
+            death_animation: true,
+            screen_shake: true,
+        }
+    }
+}
+
+struct Particle {
+    position: Point2<f32>,
+    velocity: Point2<f32>,
+    color: graphics::Color,
+    age: f32,
+    lifetime: f32,
+}
+
+#[derive(Default)]
+pub struct ParticleSystem {
+    particles: Vec<Particle>,
+}
+
+impl ParticleSystem {
+    pub fn burst(&mut self, center: Point2<f32>, count: usize, color: graphics::Color) {
+        let mut rng = rand::thread_rng();
+        for _ in 0..count {
+            let angle = rng.gen_range(0.0, std::f32::consts::PI * 2.0);
+            let speed = rng.gen_range(PARTICLE_SPEED * 0.5, PARTICLE_SPEED);
+            self.particles.push(Particle {
+                position: center,
+                velocity: Point2 {
+                    x: angle.cos() * speed,
+                    y: angle.sin() * speed,
+                },
+                color,
+                age: 0.0,
+                lifetime: rng.gen_range(PARTICLE_LIFETIME * 0.5, PARTICLE_LIFETIME),
+            });
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for particle in self.particles.iter_mut() {
+            particle.position.x += particle.velocity.x * dt;
+            particle.position.y += particle.velocity.y * dt;
+            particle.age += dt;
+        }
+        self.particles.retain(|particle| particle.age < particle.lifetime);
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    pub fn draw(&self, ctx: &mut Context, mesh: &graphics::Mesh) -> GameResult<()> {
+        for particle in self.particles.iter() {
+            let mut color = particle.color;
+            color.a = 1.0 - particle.age / particle.lifetime;
+
+            let draw_param = graphics::DrawParam::new()
+                .dest(particle.position)
+                .offset(Point2 {
+                    x: 0.5,
+                    y: 0.5,
+                })
+                .color(color);
+            graphics::draw(ctx, mesh, draw_param)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct ScreenShake {
+    intensity: f32,
+    duration: f32,
+    remaining: f32,
+}
+
+impl ScreenShake {
+    pub fn start(&mut self, intensity: f32, duration: f32) {
+        if intensity >= self.amplitude() {
+            self.intensity = intensity;
+            self.duration = duration;
+            self.remaining = duration;
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.remaining = (self.remaining - dt).max(0.0);
+    }
+
+    pub fn stop(&mut self) {
+        self.remaining = 0.0;
+    }
+
+    pub fn offset(&self) -> Point2<f32> {
+        let amplitude = self.amplitude();
+        if amplitude <= 0.0 {
+            return Point2 { x: 0.0, y: 0.0 };
+        }
+
+        let mut rng = rand::thread_rng();
+        Point2 {
+            x: rng.gen_range(-amplitude, amplitude),
+            y: rng.gen_range(-amplitude, amplitude),
+        }
+    }
+
+    fn amplitude(&self) -> f32 {
+        if self.remaining <= 0.0 {
+            0.0
+        } else {
+            self.intensity * self.remaining / self.duration
+        }
+    }
+}
+
+pub struct DeathAnimation {
+    segment_count: usize,
+    duration: f32,
+    elapsed: f32,
+    scattered: usize,
+}
+
+impl DeathAnimation {
+    pub fn new(segment_count: usize, duration: f32) -> DeathAnimation {
+        DeathAnimation {
+            segment_count,
+            duration,
+            elapsed: 0.0,
+            scattered: 0,
+        }
+    }
+
+    // Segments are counted from the tail, returns the ones that scattered during this update.
+    pub fn update(&mut self, dt: f32) -> std::ops::Range<usize> {
+        self.elapsed += dt;
+
+        let previously_scattered = self.scattered;
+        while self.scattered < self.segment_count && self.elapsed >= self.scatter_time(self.scattered) {
+            self.scattered += 1;
+        }
+        previously_scattered..self.scattered
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    pub fn is_visible(&self, index_from_tail: usize) -> bool {
+        if index_from_tail < self.scattered {
+            return false;
+        }
+
+        let time_left = self.scatter_time(index_from_tail) - self.elapsed;
+        time_left > DEATH_FLASH_DURATION || ((self.elapsed * DEATH_FLASH_FREQUENCY) as u32).is_multiple_of(2)
+    }
+
+    fn scatter_time(&self, index_from_tail: usize) -> f32 {
+        (index_from_tail + 1) as f32 * self.duration / self.segment_count as f32
+    }
+}
+
+struct Toast {
+    text: String,
+    age: f32,
+}
+
+// Short notifications shown one after the other.
+#[derive(Default)]
+pub struct Toasts {
+    toasts: VecDeque<Toast>,
+}
+
+impl Toasts {
+    pub fn push(&mut self, text: String) {
+        self.toasts.push_back(Toast { text, age: 0.0 });
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        if let Some(toast) = self.toasts.front_mut() {
+            toast.age += dt;
+            if toast.age >= TOAST_DURATION {
+                self.toasts.pop_front();
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.toasts.clear();
+    }
+
+    // Text of the toast on screen and its opacity, it fades out at the end.
+    pub fn current(&self) -> Option<(&str, f32)> {
+        self.toasts.front().map(|toast| {
+            let alpha = ((TOAST_DURATION - toast.age) / TOAST_FADE_DURATION).min(1.0);
+            (toast.text.as_str(), alpha)
+        })
+    }
+}
@@ -0,0 +1,30 @@
+use crate::board::FoodKind;
+use crate::player::DeathCause;
+use crate::utils::{Vec2D, Direction};
+
+// What a frontend is showing, the simulation itself only knows whether the snake is alive.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum GameState {
+    Start,
+    #[default]
+    Playing,
+    Dying,
+    GameOver,
+}
+
+// Things that happened during a tick, in the order they happened.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameEvent {
+    // Ends every tick the snake survived.
+    Ticked { ticks: u64 },
+    Turned { from: Direction, to: Direction },
+    Ate { kind: FoodKind, position: Vec2D },
+    // The snake got longer on this move.
+    Grew { length: usize },
+    // The snake bit itself at `position` and lost that many segments.
+    TailCut { position: Vec2D, removed: usize },
+    FoodSpawned { kind: FoodKind, position: Vec2D },
+    Died { cause: DeathCause },
+    // Sent by frontends, the simulation has no notion of screens.
+    StateChanged { from: GameState, to: GameState },
+}
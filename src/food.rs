@@ -0,0 +1,331 @@
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+
+use crate::board::{Board, Cell, FoodKind};
+use crate::utils::{Vec2D, Rectangle};
+use crate::utils::constants::*;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Food {
+    pub position: Vec2D,
+    pub kind: FoodKind,
+    // Tick at which the item disappears if it was not eaten.
+    pub expires_at: Option<u64>,
+}
+
+// Where new food items go. All of them draw from the game RNG, so they are deterministic given the seed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FoodPolicy {
+    // Any empty cell.
+    Uniform,
+    // Empty cells at least this many moves away from the head.
+    AwayFromHead(u32),
+    // Empty cells at least this many cells away from the edges of the board.
+    AvoidEdges(u32),
+    // Fixed cells used in turn, the ones taken are skipped.
+    Scheduled(Vec<Vec2D>),
+}
+
+impl FoodPolicy {
+    // Names as used on the command line and in replays, e.g. `away:4` or `points:2,3+7,7`.
+    pub fn id(&self) -> String {
+        match self {
+            FoodPolicy::Uniform => String::from("uniform"),
+            FoodPolicy::AwayFromHead(distance) => format!("away:{}", distance),
+            FoodPolicy::AvoidEdges(margin) => format!("avoid-edges:{}", margin),
+            FoodPolicy::Scheduled(points) => {
+                let points: Vec<String> = points.iter().map(|point| format!("{},{}", point.x, point.y)).collect();
+                format!("points:{}", points.join("+"))
+            }
+        }
+    }
+
+    pub fn by_id(id: &str) -> Option<FoodPolicy> {
+        let mut parts = id.splitn(2, ':');
+        let name = parts.next()?;
+        let parameter = parts.next();
+
+        match (name, parameter) {
+            ("uniform", None) => Some(FoodPolicy::Uniform),
+            ("away", None) => Some(FoodPolicy::AwayFromHead(FOOD_MIN_DISTANCE)),
+            ("away", Some(distance)) => Some(FoodPolicy::AwayFromHead(distance.parse().ok()?)),
+            ("avoid-edges", None) => Some(FoodPolicy::AvoidEdges(FOOD_EDGE_MARGIN)),
+            ("avoid-edges", Some(margin)) => Some(FoodPolicy::AvoidEdges(margin.parse().ok()?)),
+            ("points", Some(points)) => {
+                let points = points.split('+')
+                    .map(|point| {
+                        let mut coordinates = point.splitn(2, ',');
+                        let x = coordinates.next()?.parse().ok()?;
+                        let y = coordinates.next()?.parse().ok()?;
+                        Some(Vec2D::new(x, y))
+                    })
+                    .collect::<Option<Vec<Vec2D>>>()?;
+                Some(FoodPolicy::Scheduled(points))
+            }
+            _ => None,
+        }
+    }
+
+    // Picks the cell of the next food item, `None` when there is no room for it. The filtering
+    // policies fall back to any empty cell rather than leaving the board without food.
+    pub fn pick<R: Rng>(&self, board: &Board, head: Vec2D, next_point: &mut usize, rng: &mut R) -> Option<Vec2D> {
+        if let FoodPolicy::Scheduled(points) = self {
+            for offset in 0..points.len() {
+                let index = (*next_point + offset) % points.len();
+                if board.get(&points[index]) == Some(Cell::Empty) {
+                    *next_point = index + 1;
+                    return Some(points[index]);
+                }
+            }
+            return None;
+        }
+
+        let empty: Vec<Vec2D> = board.empty_cells().collect();
+        let area = board.area();
+        let preferred: Vec<Vec2D> = match self {
+            FoodPolicy::AwayFromHead(distance) => empty.iter()
+                .filter(|cell| cell.manhattan_distance(&head) >= *distance)
+                .copied()
+                .collect(),
+            FoodPolicy::AvoidEdges(margin) => empty.iter()
+                .filter(|cell| edge_distance(area, cell) >= *margin as i32)
+                .copied()
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let candidates = if preferred.is_empty() { &empty } else { &preferred };
+        if candidates.is_empty() {
+            return None;
+        }
+        Some(candidates[rng.gen_range(0, candidates.len())])
+    }
+}
+
+// Moves `food` to the neighboring empty cell farthest from the head, if that gets it farther away.
+pub fn flee(food: &mut Food, board: &mut Board, head: Vec2D) {
+    let current = food.position.manhattan_distance(&head);
+    let best = board.neighbors(&food.position)
+        .filter(|cell| board.get(cell) == Some(Cell::Empty))
+        .max_by_key(|cell| cell.manhattan_distance(&head));
+    if let Some(cell) = best.filter(|cell| cell.manhattan_distance(&head) > current) {
+        board.set(&food.position, Cell::Empty);
+        board.set(&cell, Cell::Food(food.kind));
+        food.position = cell;
+    }
+}
+
+fn edge_distance(area: &Rectangle, cell: &Vec2D) -> i32 {
+    (cell.x - area.min.x)
+        .min(area.max.x - cell.x)
+        .min(cell.y - area.min.y)
+        .min(area.max.y - cell.y)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FoodConf {
+    // Number of items on the board at once.
+    pub count: usize,
+    pub policy: FoodPolicy,
+    // Ticks an item stays on the board before it disappears, forever when `None`.
+    pub lifetime: Option<u32>,
+    // Ticks before an eaten or expired item is replaced.
+    pub respawn_delay: u32,
+    // Items step away from the head every that many ticks.
+    pub flee_every: Option<u32>,
+}
+
+impl Default for FoodConf {
+    fn default() -> FoodConf {
+        FoodConf {
+            count: 1,
+            policy: FoodPolicy::Uniform,
+            lifetime: None,
+            respawn_delay: 0,
+            flee_every: None,
+        }
+    }
+}
+
+impl FoodConf {
+    // Used in replay headers, e.g. `3/away:4/40/5/2` or `1/uniform/-/0/-`.
+    pub fn id(&self) -> String {
+        let optional = |value: Option<u32>| match value {
+            Some(value) => value.to_string(),
+            None => String::from("-"),
+        };
+        format!(
+            "{}/{}/{}/{}/{}",
+            self.count,
+            self.policy.id(),
+            optional(self.lifetime),
+            self.respawn_delay,
+            optional(self.flee_every)
+        )
+    }
+
+    // Older replays have no fleeing setting.
+    pub fn by_id(id: &str) -> Option<FoodConf> {
+        let parts: Vec<&str> = id.split('/').collect();
+        if parts.len() != 4 && parts.len() != 5 {
+            return None;
+        }
+        let optional = |part: &str| match part {
+            "-" => Some(None),
+            value => value.parse().ok().map(Some),
+        };
+        Some(FoodConf {
+            count: parts[0].parse().ok()?,
+            policy: FoodPolicy::by_id(parts[1])?,
+            lifetime: optional(parts[2])?,
+            respawn_delay: parts[3].parse().ok()?,
+            flee_every: match parts.get(4) {
+                Some(part) => optional(part)?,
+                None => None,
+            },
+        })
+    }
+
+    pub fn check(&self, area: &Rectangle) -> Result<(), String> {
+        if self.count == 0 {
+            return Err(String::from("There has to be at least one food item"));
+        }
+        if self.lifetime == Some(0) {
+            return Err(String::from("Food lifetime has to be at least one tick"));
+        }
+        if self.flee_every == Some(0) {
+            return Err(String::from("Fleeing food has to wait at least one tick between steps"));
+        }
+        if let FoodPolicy::Scheduled(points) = &self.policy {
+            if let Some(point) = points.iter().find(|point| !area.contains(point)) {
+                return Err(format!("Food spawn point ({}, {}) is outside of the board", point.x, point.y));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_pcg::Pcg32;
+
+    use crate::game::Game;
+    use crate::hazard::HazardConf;
+    use crate::mode::{GameMode, Zen};
+    use crate::spawn::SpawnConf;
+
+    fn policies() -> Vec<FoodPolicy> {
+        vec![
+            FoodPolicy::Uniform,
+            FoodPolicy::AwayFromHead(6),
+            FoodPolicy::AvoidEdges(2),
+            FoodPolicy::Scheduled(vec![Vec2D::new(1, 2), Vec2D::new(-3, 7)]),
+        ]
+    }
+
+    #[test]
+    fn policy_ids_round_trip() {
+        for policy in policies() {
+            assert_eq!(FoodPolicy::by_id(&policy.id()), Some(policy));
+        }
+        assert_eq!(FoodPolicy::by_id("away"), Some(FoodPolicy::AwayFromHead(FOOD_MIN_DISTANCE)));
+        assert_eq!(FoodPolicy::by_id("avoid-edges"), Some(FoodPolicy::AvoidEdges(FOOD_EDGE_MARGIN)));
+        for id in ["", "uniform:1", "away:far", "points", "points:1,2+3", "everywhere"].iter() {
+            assert_eq!(FoodPolicy::by_id(id), None, "{}", id);
+        }
+    }
+
+    #[test]
+    fn conf_ids_round_trip() {
+        for policy in policies() {
+            let conf = FoodConf {
+                count: 3,
+                policy,
+                lifetime: Some(40),
+                respawn_delay: 5,
+                flee_every: Some(2),
+            };
+            assert_eq!(FoodConf::by_id(&conf.id()), Some(conf));
+        }
+        assert_eq!(FoodConf::by_id(&FoodConf::default().id()), Some(FoodConf::default()));
+        // Replays written before fleeing food existed.
+        assert_eq!(FoodConf::by_id("1/uniform/-/0"), Some(FoodConf::default()));
+        assert_eq!(FoodConf::by_id("1/uniform/-"), None);
+    }
+
+    #[test]
+    fn away_from_head_keeps_its_distance() {
+        let board = Board::new(&Rectangle::new(0, 0, 9, 9));
+        let head = Vec2D::new(5, 5);
+        let mut rng = Pcg32::seed_from_u64(3);
+        for _ in 0..100 {
+            let cell = FoodPolicy::AwayFromHead(6).pick(&board, head, &mut 0, &mut rng).unwrap();
+            assert!(cell.manhattan_distance(&head) >= 6);
+        }
+    }
+
+    #[test]
+    fn avoid_edges_keeps_its_margin() {
+        let area = Rectangle::new(0, 0, 9, 9);
+        let board = Board::new(&area);
+        let mut rng = Pcg32::seed_from_u64(3);
+        for _ in 0..100 {
+            let cell = FoodPolicy::AvoidEdges(2).pick(&board, Vec2D::new(0, 0), &mut 0, &mut rng).unwrap();
+            assert!(edge_distance(&area, &cell) >= 2);
+        }
+    }
+
+    #[test]
+    fn filtering_policies_fall_back_to_any_empty_cell() {
+        let mut board = Board::new(&Rectangle::new(0, 0, 2, 2));
+        for cell in board.area().cells().collect::<Vec<Vec2D>>() {
+            if cell != Vec2D::new(0, 0) {
+                board.set(&cell, Cell::Wall);
+            }
+        }
+        let mut rng = Pcg32::seed_from_u64(3);
+        let pick = |policy: FoodPolicy, rng: &mut Pcg32| policy.pick(&board, Vec2D::new(1, 1), &mut 0, rng);
+        assert_eq!(pick(FoodPolicy::AwayFromHead(10), &mut rng), Some(Vec2D::new(0, 0)));
+        assert_eq!(pick(FoodPolicy::AvoidEdges(5), &mut rng), Some(Vec2D::new(0, 0)));
+    }
+
+    #[test]
+    fn scheduled_points_are_used_in_turn_skipping_taken_ones() {
+        let mut board = Board::new(&Rectangle::new(0, 0, 9, 9));
+        let points = vec![Vec2D::new(1, 1), Vec2D::new(2, 2), Vec2D::new(3, 3)];
+        let policy = FoodPolicy::Scheduled(points.clone());
+        let mut rng = Pcg32::seed_from_u64(3);
+        let mut next_point = 0;
+
+        assert_eq!(policy.pick(&board, Vec2D::new(0, 0), &mut next_point, &mut rng), Some(points[0]));
+        board.set(&points[1], Cell::Wall);
+        assert_eq!(policy.pick(&board, Vec2D::new(0, 0), &mut next_point, &mut rng), Some(points[2]));
+        assert_eq!(policy.pick(&board, Vec2D::new(0, 0), &mut next_point, &mut rng), Some(points[0]));
+
+        for point in points.iter() {
+            board.set(point, Cell::Wall);
+        }
+        assert_eq!(policy.pick(&board, Vec2D::new(0, 0), &mut next_point, &mut rng), None);
+    }
+
+    // Food positions over a run where the snake keeps going around the board.
+    fn food_positions(policy: FoodPolicy, seed: u64) -> Vec<Vec<Vec2D>> {
+        let conf = FoodConf { count: 3, policy, lifetime: Some(7), respawn_delay: 2, flee_every: None };
+        let area = Rectangle::new(0, 0, 11, 11);
+        let mut game = Game::new(area, SpawnConf::default(), conf, HazardConf::default(), Vec::new(), GameMode::Zen(Zen), seed).unwrap();
+        (0..60).map(|_| {
+            game.tick();
+            game.food.iter().map(|food| food.position).collect()
+        }).collect()
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_food() {
+        for policy in policies().into_iter().take(3) {
+            assert_eq!(food_positions(policy.clone(), 11), food_positions(policy.clone(), 11));
+            assert_ne!(food_positions(policy.clone(), 11), food_positions(policy, 12));
+        }
+    }
+}
@@ -0,0 +1,491 @@
+use rand::SeedableRng;
+use rand_pcg::Pcg32;
+use serde::{Serialize, Deserialize};
+
+use crate::board::{Board, Cell, FoodKind};
+use crate::events::GameEvent;
+use crate::food::{self, Food, FoodConf};
+use crate::hazard::{Hazard, HazardConf};
+use crate::mode::{GameMode, Variants, Collision};
+use crate::player::{Player, DeathCause};
+use crate::spawn::{self, SpawnConf, SpawnError};
+use crate::utils::{Vec2D, Rectangle};
+use crate::utils::constants::{MIN_LENGTH, TAIL_CUT_PENALTY};
+
+pub enum TickOutcome {
+    Moved,
+    Ate(Vec2D),
+    // The rules kept the snake in place instead of killing it.
+    Blocked(DeathCause),
+    // The snake bit itself at the given cell and lost that many segments.
+    Cut(Vec2D, usize),
+    Died(DeathCause),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Game {
+    pub player: Player,
+    pub playing_area: Rectangle,
+    pub board: Board,
+    pub food: Vec<Food>,
+    pub score: u32,
+    pub spawn: SpawnConf,
+    pub food_conf: FoodConf,
+    pub hazards: Vec<Hazard>,
+    pub hazard_conf: HazardConf,
+    pub mode: GameMode,
+    pub variants: Variants,
+    seed: u64,
+    ticks: u64,
+    rng: Pcg32,
+    // Ticks at which missing food items come back.
+    food_timers: Vec<u64>,
+    next_food_point: usize,
+    // Saves from before it was counted resume at zero.
+    #[serde(default)]
+    food_eaten: u64,
+    // Events of the last `tick` or `reset`.
+    #[serde(skip)]
+    events: Vec<GameEvent>,
+}
+
+impl Game {
+    pub fn new(
+        playing_area: Rectangle,
+        spawn: SpawnConf,
+        food_conf: FoodConf,
+        hazard_conf: HazardConf,
+        portals: Vec<(Vec2D, Vec2D)>,
+        mode: GameMode,
+        seed: u64
+    ) -> Result<Game, SpawnError> {
+        let mut board = Board::new(&playing_area);
+        for (first, second) in portals {
+            board.add_portal(first, second);
+        }
+        // Portals can take the cells the snake needs, the seed only picks among the spawns left.
+        spawn::find_spawn(&board, &spawn, &mut Pcg32::seed_from_u64(seed))?;
+        let mut game = Game {
+            player: Player::new(0),
+            board,
+            playing_area,
+            food: Vec::new(),
+            score: 0,
+            spawn,
+            food_conf,
+            hazards: Vec::new(),
+            hazard_conf,
+            mode,
+            variants: Variants::default(),
+            seed,
+            ticks: 0,
+            rng: Pcg32::seed_from_u64(seed),
+            food_timers: Vec::new(),
+            next_food_point: 0,
+            food_eaten: 0,
+            events: Vec::new(),
+        };
+        game.reset(seed);
+        Ok(game)
+    }
+
+    // Only portals are left on the board when spawning, and `new` made sure the snake fits next to them.
+    pub fn reset(&mut self, seed: u64) {
+        self.events.clear();
+        self.seed = seed;
+        self.ticks = 0;
+        self.score = 0;
+        self.food_eaten = 0;
+        self.rng = Pcg32::seed_from_u64(seed);
+        self.board.clear();
+        let spawn = spawn::find_spawn(&self.board, &self.spawn, &mut self.rng)
+            .expect("Failed to spawn the snake");
+        self.player.reset(&mut self.board, &spawn);
+        self.hazards = self.hazard_conf.place(&mut self.board, spawn.head, &mut self.rng);
+        self.food.clear();
+        self.food_timers.clear();
+        self.next_food_point = 0;
+        if self.mode.rules().has_food() {
+            for _ in 0..self.food_conf.count {
+                self.spawn_food();
+            }
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // Food items eaten since the last reset.
+    pub fn food_eaten(&self) -> u64 {
+        self.food_eaten
+    }
+
+    // Ticks since the last reset, including the one the snake died on.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    pub fn events(&self) -> &[GameEvent] {
+        &self.events
+    }
+
+    pub fn tick(&mut self) -> TickOutcome {
+        self.events.clear();
+        let outcome = self.play_tick();
+        let event = match outcome {
+            TickOutcome::Died(cause) => GameEvent::Died { cause },
+            _ => GameEvent::Ticked { ticks: self.ticks },
+        };
+        self.events.push(event);
+        outcome
+    }
+
+    fn play_tick(&mut self) -> TickOutcome {
+        let mode = self.mode;
+        let rules = mode.rules();
+        if let Some(cause) = rules.check_end(self.ticks) {
+            return TickOutcome::Died(cause);
+        }
+
+        self.ticks += 1;
+        let previous_direction = self.player.direction;
+        if let Some(direction) = self.player.wanted_direction {
+            if direction != self.player.direction.opposite() {
+                self.player.direction = direction;
+            }
+        }
+
+        let mut cut = None;
+        if let Err(cause) = self.player.check_move(&self.board, rules.tail_is_free()) {
+            let mut collision = self.variants.collision(rules.collision(&cause), &cause);
+            // Through a portal the head can land on its neck, cutting there would leave less than a snake.
+            // Modes that cut the tail on their own block instead, the variant dies.
+            if let (Collision::CutTail, DeathCause::HitSelf(_, index)) = (collision, cause) {
+                if index < MIN_LENGTH {
+                    collision = match rules.collision(&cause) {
+                        Collision::CutTail => Collision::Block,
+                        _ => Collision::Die,
+                    };
+                }
+            }
+            match (collision, cause) {
+                (Collision::Block, _) => {
+                    // The snake did not turn, so the next turn is checked against the way it really goes.
+                    self.player.direction = previous_direction;
+                    if let Some(cause) = self.update_world() {
+                        return TickOutcome::Died(cause);
+                    }
+                    return TickOutcome::Blocked(cause);
+                }
+                (Collision::CutTail, DeathCause::HitSelf(position, index)) => {
+                    let removed = self.player.cut_at(index, &mut self.board);
+                    self.score = self.score.saturating_sub(removed as u32 * TAIL_CUT_PENALTY);
+                    self.events.push(GameEvent::TailCut { position, removed });
+                    cut = Some(TickOutcome::Cut(position, removed));
+                }
+                _ => return TickOutcome::Died(cause),
+            }
+        }
+
+        if self.player.direction != previous_direction {
+            self.events.push(GameEvent::Turned { from: previous_direction, to: self.player.direction });
+        }
+        let length = self.player.body.len();
+        self.player.r#move(&mut self.board);
+        debug_assert_eq!(self.player.validate_body(&self.board), Ok(()));
+        if self.player.body.len() > length {
+            self.events.push(GameEvent::Grew { length: self.player.body.len() });
+        }
+
+        let growth = rules.growth(self.ticks);
+        if growth > 0 {
+            self.player.grow(growth);
+            self.score += growth as u32;
+        }
+
+        let head = self.player.body[0].position;
+        let eaten = self.food.iter().position(|food| food.position == head);
+        if let Some(index) = eaten {
+            let food = self.food.remove(index);
+            self.events.push(GameEvent::Ate { kind: food.kind, position: head });
+            self.player.eat();
+            self.score += 1;
+            self.food_eaten += 1;
+            if rules.has_food() {
+                self.schedule_food();
+            }
+        }
+        if let Some(cause) = self.update_world() {
+            return TickOutcome::Died(cause);
+        }
+
+        match eaten {
+            Some(_) => TickOutcome::Ate(head),
+            None => cut.unwrap_or(TickOutcome::Moved),
+        }
+    }
+
+    // Cell to highlight after a death, the head stands in for cells outside of the board.
+    pub fn fatal_cell(&self, cause: &DeathCause) -> Option<Vec2D> {
+        match cause.position() {
+            Some(position) if self.playing_area.contains(&position) => Some(position),
+            Some(_) => Some(self.player.body[0].position),
+            None => None,
+        }
+    }
+
+    // Moves the oldest food item to `position`, which has to be an empty cell.
+    pub fn place_food(&mut self, position: Vec2D) -> bool {
+        if self.board.get(&position) != Some(Cell::Empty) {
+            return false;
+        }
+        if !self.food.is_empty() {
+            let oldest = self.food.remove(0);
+            self.board.set(&oldest.position, Cell::Empty);
+        }
+        self.add_food(position);
+        true
+    }
+
+    // Moves everything but the player, returns why the snake died when a hazard ran into it.
+    fn update_world(&mut self) -> Option<DeathCause> {
+        self.update_food();
+
+        let head = self.player.body[0].position;
+        if let Some(flee_every) = self.food_conf.flee_every {
+            if self.ticks.is_multiple_of(flee_every as u64) {
+                for item in self.food.iter_mut() {
+                    food::flee(item, &mut self.board, head);
+                }
+            }
+        }
+
+        let contact = DeathCause::HitHazard(head);
+        let deadly = self.variants.collision(self.mode.rules().collision(&contact), &contact) == Collision::Die;
+        for hazard in self.hazards.iter_mut() {
+            if hazard.update(&self.hazard_conf, &mut self.board, self.ticks, head, deadly) {
+                return Some(contact);
+            }
+        }
+        None
+    }
+
+    // Removes the expired items and brings back the ones whose timer ran out.
+    fn update_food(&mut self) {
+        let ticks = self.ticks;
+        let (expired, kept): (Vec<Food>, Vec<Food>) = self.food.drain(..)
+            .partition(|food| food.expires_at.is_some_and(|expires_at| expires_at <= ticks));
+        self.food = kept;
+        for food in expired {
+            self.board.set(&food.position, Cell::Empty);
+            self.schedule_food();
+        }
+
+        let due = self.food_timers.iter().filter(|&&at| at <= ticks).count();
+        self.food_timers.retain(|&at| at > ticks);
+        for _ in 0..due {
+            self.spawn_food();
+        }
+    }
+
+    fn schedule_food(&mut self) {
+        self.food_timers.push(self.ticks + self.food_conf.respawn_delay as u64);
+    }
+
+    // Tries again on the next tick when the policy finds no room.
+    fn spawn_food(&mut self) {
+        let head = self.player.body[0].position;
+        match self.food_conf.policy.pick(&self.board, head, &mut self.next_food_point, &mut self.rng) {
+            Some(position) => self.add_food(position),
+            None => self.food_timers.push(self.ticks + 1),
+        }
+    }
+
+    fn add_food(&mut self, position: Vec2D) {
+        self.board.set(&position, Cell::Food(FoodKind::Regular));
+        let expires_at = self.food_conf.lifetime.map(|lifetime| self.ticks + lifetime as u64);
+        self.food.push(Food {
+            position,
+            kind: FoodKind::Regular,
+            expires_at,
+        });
+        self.events.push(GameEvent::FoodSpawned { kind: FoodKind::Regular, position });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mode::Zen;
+    use crate::spawn::Placement;
+    use crate::utils::Direction;
+
+    fn zen_game() -> Game {
+        let area = Rectangle::new(0, 0, 9, 9);
+        Game::new(area, SpawnConf::default(), FoodConf::default(), HazardConf::default(), Vec::new(), GameMode::Zen(Zen), 5)
+            .unwrap()
+    }
+
+    fn steer_until(game: &mut Game, direction: Direction, done: impl Fn(Vec2D) -> bool) {
+        game.player.wanted_direction = Some(direction);
+        while !done(game.player.body[0].position) {
+            game.tick();
+        }
+    }
+
+    #[test]
+    fn blocked_turn_does_not_allow_reversing_into_the_neck() {
+        let mut game = zen_game();
+        steer_until(&mut game, Direction::Up, |head| head.y == 0);
+        steer_until(&mut game, Direction::Left, |head| head.x == 0);
+        let body: Vec<Vec2D> = game.player.body.iter().map(|body_part| body_part.position).collect();
+
+        // Up is a wall, and once that turn failed the snake still goes left, so right is a reversal.
+        for direction in [Direction::Up, Direction::Right].iter() {
+            game.player.wanted_direction = Some(*direction);
+            assert!(matches!(game.tick(), TickOutcome::Blocked(DeathCause::HitWall(_))));
+            assert_eq!(game.player.direction, Direction::Left);
+        }
+        let after: Vec<Vec2D> = game.player.body.iter().map(|body_part| body_part.position).collect();
+        assert_eq!(after, body);
+        assert_eq!(game.player.validate_body(&game.board), Ok(()));
+    }
+
+    // A snake of 8 heading right with its head on (12, 10), and no food in the way.
+    fn looping_game(mode: GameMode, variants: Variants) -> Game {
+        let area = Rectangle::new(0, 0, 19, 19);
+        let spawn = SpawnConf { length: 8, ..SpawnConf::default() };
+        let mut game = Game::new(area, spawn, FoodConf::default(), HazardConf::default(), Vec::new(), mode, 5)
+            .unwrap();
+        game.variants = variants;
+        for food in game.food.drain(..) {
+            game.board.set(&food.position, Cell::Empty);
+        }
+        game.score = 10;
+        game
+    }
+
+    // Turns back on the body, the head ends up on (11, 10), the fourth segment.
+    fn bite(game: &mut Game) -> TickOutcome {
+        for direction in [Direction::Up, Direction::Left].iter() {
+            game.player.wanted_direction = Some(*direction);
+            assert!(matches!(game.tick(), TickOutcome::Moved));
+        }
+        game.player.wanted_direction = Some(Direction::Down);
+        game.tick()
+    }
+
+    fn assert_tail_cut(mut game: Game) {
+        let bitten = Vec2D::new(11, 10);
+        assert!(matches!(bite(&mut game), TickOutcome::Cut(position, 5) if position == bitten));
+        assert_eq!(game.player.body.len(), 3);
+        assert_eq!(game.player.body[0].position, bitten);
+        assert_eq!(game.score, 10 - 5 * TAIL_CUT_PENALTY);
+        assert_eq!(game.events(), &[
+            GameEvent::TailCut { position: bitten, removed: 5 },
+            GameEvent::Turned { from: Direction::Left, to: Direction::Down },
+            GameEvent::Ticked { ticks: 3 },
+        ]);
+        let tail = game.player.body.back().unwrap();
+        assert!(tail.is_tail);
+        assert_eq!(tail.orientation, (Some(Direction::Left), None));
+        assert_eq!(game.player.validate_body(&game.board), Ok(()));
+        assert_eq!(game.board.free_cell_count(), 400 - 3);
+    }
+
+    #[test]
+    fn zen_cuts_the_tail_on_a_bite() {
+        assert_tail_cut(looping_game(GameMode::Zen(Zen), Variants::default()));
+    }
+
+    #[test]
+    fn tail_cutting_variant_cuts_the_tail_on_a_bite() {
+        assert_tail_cut(looping_game(GameMode::default(), Variants { tail_cutting: true }));
+    }
+
+    #[test]
+    fn bites_kill_without_the_variant() {
+        let mut game = looping_game(GameMode::default(), Variants::default());
+        assert!(matches!(bite(&mut game), TickOutcome::Died(DeathCause::HitSelf(_, 3))));
+        assert_eq!(game.player.body.len(), 8);
+    }
+
+    fn game_with_portal(placement: Placement, seed: u64) -> Result<Game, SpawnError> {
+        let area = Rectangle::new(0, 0, 9, 9);
+        let spawn = SpawnConf { placement, ..SpawnConf::default() };
+        let portals = vec![(Vec2D::new(5, 5), Vec2D::new(0, 0))];
+        Game::new(area, spawn, FoodConf::default(), HazardConf::default(), portals, GameMode::default(), seed)
+    }
+
+    #[test]
+    fn portal_on_the_spawn_row_is_refused() {
+        assert_eq!(game_with_portal(Placement::Centered, 1).err(), Some(SpawnError::NoRoom));
+    }
+
+    #[test]
+    fn random_spawns_go_around_portals() {
+        for seed in 0..10 {
+            let mut game = game_with_portal(Placement::Random, seed).unwrap();
+            game.reset(seed + 100);
+            assert_eq!(game.player.validate_body(&game.board), Ok(()));
+        }
+    }
+
+    // A snake of 4 heading right with its head on (10, 10), going down leads back onto its neck.
+    fn neck_portal_game(mode: GameMode, variants: Variants) -> Game {
+        let area = Rectangle::new(0, 0, 19, 19);
+        let spawn = SpawnConf { length: 4, ..SpawnConf::default() };
+        let portals = vec![(Vec2D::new(10, 11), Vec2D::new(9, 9))];
+        let mut game = Game::new(area, spawn, FoodConf::default(), HazardConf::default(), portals, mode, 5)
+            .unwrap();
+        game.variants = variants;
+        assert_eq!(game.player.body[0].position, Vec2D::new(10, 10));
+        game.player.wanted_direction = Some(Direction::Down);
+        game
+    }
+
+    #[test]
+    fn zen_blocks_on_a_bite_through_a_portal_onto_the_neck() {
+        let mut game = neck_portal_game(GameMode::Zen(Zen), Variants::default());
+        let neck = Vec2D::new(9, 10);
+        assert!(matches!(game.tick(), TickOutcome::Blocked(DeathCause::HitSelf(position, 1)) if position == neck));
+        assert_eq!(game.player.body.len(), 4);
+        assert_eq!(game.player.direction, Direction::Right);
+        assert_eq!(game.player.validate_body(&game.board), Ok(()));
+    }
+
+    #[test]
+    fn tail_cutting_dies_on_a_bite_through_a_portal_onto_the_neck() {
+        let mut game = neck_portal_game(GameMode::default(), Variants { tail_cutting: true });
+        assert!(matches!(game.tick(), TickOutcome::Died(DeathCause::HitSelf(_, 1))));
+        assert_eq!(game.player.body.len(), 4);
+        assert_eq!(game.player.validate_body(&game.board), Ok(()));
+    }
+
+    #[test]
+    fn ticks_report_what_happened_in_order() {
+        let mut game = looping_game(GameMode::default(), Variants::default());
+        assert!(game.place_food(Vec2D::new(12, 9)));
+
+        game.player.wanted_direction = Some(Direction::Up);
+        game.tick();
+        let spawned = game.food[0].position;
+        assert_eq!(game.events(), &[
+            GameEvent::Turned { from: Direction::Right, to: Direction::Up },
+            GameEvent::Ate { kind: FoodKind::Regular, position: Vec2D::new(12, 9) },
+            GameEvent::FoodSpawned { kind: FoodKind::Regular, position: spawned },
+            GameEvent::Ticked { ticks: 1 },
+        ]);
+
+        // The segment from the food is added on the next move.
+        game.tick();
+        assert_eq!(game.events(), &[GameEvent::Grew { length: 9 }, GameEvent::Ticked { ticks: 2 }]);
+
+        while let TickOutcome::Moved | TickOutcome::Ate(_) = game.tick() {}
+        assert_eq!(game.events(), &[GameEvent::Died { cause: DeathCause::HitWall(Vec2D::new(12, -1)) }]);
+
+        game.reset(3);
+        assert!(matches!(game.events(), [GameEvent::FoodSpawned { .. }]));
+    }
+}
@@ -0,0 +1,221 @@
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+
+use crate::board::{Board, Cell};
+use crate::utils::Vec2D;
+use crate::utils::constants::*;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Hazard {
+    // Moves diagonally and bounces off anything in its way.
+    Bouncer { position: Vec2D, velocity: Vec2D },
+    // A wall that comes and goes, it only comes back once its cell is empty.
+    TemporaryWall { position: Vec2D, is_up: bool, next_switch: u64 },
+}
+
+impl Hazard {
+    pub fn position(&self) -> Vec2D {
+        match self {
+            Hazard::Bouncer { position, .. } | Hazard::TemporaryWall { position, .. } => *position,
+        }
+    }
+
+    // Advances the hazard to tick `ticks`. Returns true when a bouncer ran into the head, it
+    // bounces off it instead when it is not `deadly`.
+    pub fn update(&mut self, conf: &HazardConf, board: &mut Board, ticks: u64, head: Vec2D, deadly: bool) -> bool {
+        match self {
+            Hazard::Bouncer { position, velocity } => {
+                if !ticks.is_multiple_of(conf.bounce_every as u64) {
+                    return false;
+                }
+                let flips = [Vec2D::new(1, 1), Vec2D::new(-1, 1), Vec2D::new(1, -1), Vec2D::new(-1, -1)];
+                for flip in flips.iter() {
+                    let candidate = Vec2D::new(velocity.x * flip.x, velocity.y * flip.y);
+                    let dest = *position + candidate;
+                    if dest == head && deadly {
+                        return true;
+                    }
+                    if board.get(&dest) == Some(Cell::Empty) {
+                        board.set(position, Cell::Empty);
+                        board.set(&dest, Cell::Hazard);
+                        *position = dest;
+                        *velocity = candidate;
+                        break;
+                    }
+                }
+                false
+            }
+            Hazard::TemporaryWall { position, is_up, next_switch } => {
+                if ticks < *next_switch {
+                    return false;
+                }
+                if *is_up {
+                    board.set(position, Cell::Empty);
+                    *is_up = false;
+                    *next_switch = ticks + conf.wall_down as u64;
+                } else if board.get(position) == Some(Cell::Empty) {
+                    board.set(position, Cell::Wall);
+                    *is_up = true;
+                    *next_switch = ticks + conf.wall_up as u64;
+                }
+                false
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HazardConf {
+    pub bouncers: usize,
+    // Bouncers move every that many ticks.
+    pub bounce_every: u32,
+    pub temporary_walls: usize,
+    // Ticks the temporary walls stay up, then down.
+    pub wall_up: u32,
+    pub wall_down: u32,
+}
+
+impl Default for HazardConf {
+    fn default() -> HazardConf {
+        HazardConf {
+            bouncers: 0,
+            bounce_every: BOUNCE_EVERY,
+            temporary_walls: 0,
+            wall_up: WALL_UP_TICKS,
+            wall_down: WALL_DOWN_TICKS,
+        }
+    }
+}
+
+impl HazardConf {
+    // Used in replay headers: bouncers, their period, walls and their timing, e.g. `2/2/3/20/20`.
+    pub fn id(&self) -> String {
+        format!(
+            "{}/{}/{}/{}/{}",
+            self.bouncers, self.bounce_every, self.temporary_walls, self.wall_up, self.wall_down
+        )
+    }
+
+    pub fn by_id(id: &str) -> Option<HazardConf> {
+        let parts: Vec<&str> = id.split('/').collect();
+        if parts.len() != 5 {
+            return None;
+        }
+        Some(HazardConf {
+            bouncers: parts[0].parse().ok()?,
+            bounce_every: parts[1].parse().ok()?,
+            temporary_walls: parts[2].parse().ok()?,
+            wall_up: parts[3].parse().ok()?,
+            wall_down: parts[4].parse().ok()?,
+        })
+    }
+
+    pub fn check(&self) -> Result<(), String> {
+        if self.bounce_every == 0 || self.wall_up == 0 || self.wall_down == 0 {
+            return Err(String::from("Hazard timings have to be at least one tick"));
+        }
+        Ok(())
+    }
+
+    // Puts the hazards on empty cells away from the head, so the snake does not spawn into one.
+    pub fn place<R: Rng>(&self, board: &mut Board, head: Vec2D, rng: &mut R) -> Vec<Hazard> {
+        let mut hazards = Vec::new();
+        for i in 0..self.bouncers + self.temporary_walls {
+            let candidates: Vec<Vec2D> = board.empty_cells()
+                .filter(|cell| cell.manhattan_distance(&head) >= HAZARD_SPAWN_DISTANCE)
+                .collect();
+            if candidates.is_empty() {
+                break;
+            }
+            let position = candidates[rng.gen_range(0, candidates.len())];
+            if i < self.bouncers {
+                let velocity = Vec2D::new(if rng.gen() { 1 } else { -1 }, if rng.gen() { 1 } else { -1 });
+                board.set(&position, Cell::Hazard);
+                hazards.push(Hazard::Bouncer { position, velocity });
+            } else {
+                board.set(&position, Cell::Wall);
+                hazards.push(Hazard::TemporaryWall { position, is_up: true, next_switch: self.wall_up as u64 });
+            }
+        }
+        hazards
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::Rectangle;
+
+    fn conf() -> HazardConf {
+        HazardConf { bouncers: 1, bounce_every: 1, temporary_walls: 1, wall_up: 5, wall_down: 3 }
+    }
+
+    fn board_with(cells: &[(Vec2D, Cell)]) -> Board {
+        let mut board = Board::new(&Rectangle::new(0, 0, 9, 9));
+        for (position, cell) in cells {
+            board.set(position, *cell);
+        }
+        board
+    }
+
+    #[test]
+    fn bouncer_kills_the_head_it_runs_into() {
+        let head = Vec2D::new(6, 6);
+        let mut board = board_with(&[(Vec2D::new(5, 5), Cell::Hazard), (head, Cell::Snake(0, 0))]);
+        let mut bouncer = Hazard::Bouncer { position: Vec2D::new(5, 5), velocity: Vec2D::new(1, 1) };
+        assert!(bouncer.update(&conf(), &mut board, 1, head, true));
+        assert_eq!(bouncer.position(), Vec2D::new(5, 5));
+    }
+
+    #[test]
+    fn bouncer_bounces_off_the_head_when_not_deadly() {
+        let head = Vec2D::new(6, 6);
+        let mut board = board_with(&[(Vec2D::new(5, 5), Cell::Hazard), (head, Cell::Snake(0, 0))]);
+        let mut bouncer = Hazard::Bouncer { position: Vec2D::new(5, 5), velocity: Vec2D::new(1, 1) };
+        assert!(!bouncer.update(&conf(), &mut board, 1, head, false));
+        assert_eq!(bouncer, Hazard::Bouncer { position: Vec2D::new(4, 6), velocity: Vec2D::new(-1, 1) });
+        assert_eq!(board.get(&Vec2D::new(5, 5)), Some(Cell::Empty));
+        assert_eq!(board.get(&Vec2D::new(4, 6)), Some(Cell::Hazard));
+        assert_eq!(board.get(&head), Some(Cell::Snake(0, 0)));
+    }
+
+    #[test]
+    fn bouncer_waits_between_moves() {
+        let mut board = board_with(&[(Vec2D::new(5, 5), Cell::Hazard)]);
+        let conf = HazardConf { bounce_every: 2, ..conf() };
+        let mut bouncer = Hazard::Bouncer { position: Vec2D::new(5, 5), velocity: Vec2D::new(1, 1) };
+        bouncer.update(&conf, &mut board, 1, Vec2D::new(0, 0), true);
+        assert_eq!(bouncer.position(), Vec2D::new(5, 5));
+        bouncer.update(&conf, &mut board, 2, Vec2D::new(0, 0), true);
+        assert_eq!(bouncer.position(), Vec2D::new(6, 6));
+    }
+
+    #[test]
+    fn temporary_wall_stays_down_while_its_cell_is_taken() {
+        let position = Vec2D::new(3, 3);
+        let mut board = board_with(&[(position, Cell::Snake(0, 0))]);
+        let mut wall = Hazard::TemporaryWall { position, is_up: false, next_switch: 10 };
+        for ticks in 10..13 {
+            wall.update(&conf(), &mut board, ticks, Vec2D::new(0, 0), true);
+            assert_eq!(wall, Hazard::TemporaryWall { position, is_up: false, next_switch: 10 });
+            assert_eq!(board.get(&position), Some(Cell::Snake(0, 0)));
+        }
+
+        board.set(&position, Cell::Empty);
+        wall.update(&conf(), &mut board, 13, Vec2D::new(0, 0), true);
+        assert_eq!(wall, Hazard::TemporaryWall { position, is_up: true, next_switch: 18 });
+        assert_eq!(board.get(&position), Some(Cell::Wall));
+    }
+
+    #[test]
+    fn temporary_wall_goes_down_on_time() {
+        let position = Vec2D::new(3, 3);
+        let mut board = board_with(&[(position, Cell::Wall)]);
+        let mut wall = Hazard::TemporaryWall { position, is_up: true, next_switch: 5 };
+        wall.update(&conf(), &mut board, 4, Vec2D::new(0, 0), true);
+        assert_eq!(board.get(&position), Some(Cell::Wall));
+        wall.update(&conf(), &mut board, 5, Vec2D::new(0, 0), true);
+        assert_eq!(wall, Hazard::TemporaryWall { position, is_up: false, next_switch: 8 });
+        assert_eq!(board.get(&position), Some(Cell::Empty));
+    }
+}
@@ -0,0 +1,176 @@
+use std::fs;
+use std::path::Path;
+
+use crate::utils::{Vec2D, Rectangle};
+
+// A board layout read from a text file, one character per cell:
+// `.` is an empty cell, `*` a food spawn point, and each letter appears twice to mark a pair of portals.
+pub struct Level {
+    pub width: u32,
+    pub height: u32,
+    pub portals: Vec<(Vec2D, Vec2D)>,
+    pub food_points: Vec<Vec2D>,
+}
+
+impl Level {
+    pub fn load(path: &Path) -> Result<Level, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Level::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> Result<Level, String> {
+        let rows: Vec<&str> = content.lines().map(str::trim_end).filter(|row| !row.is_empty()).collect();
+        let width = rows.first().map_or(0, |row| row.chars().count());
+        if width == 0 || rows.iter().any(|row| row.chars().count() != width) {
+            return Err(String::from("Level rows must all have the same, non-zero length"));
+        }
+
+        let mut portal_ends: Vec<(char, Vec<Vec2D>)> = Vec::new();
+        let mut food_points = Vec::new();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let position = Vec2D::new(x as i32, y as i32);
+                match c {
+                    '.' => (),
+                    '*' => food_points.push(position),
+                    c if c.is_ascii_alphabetic() => match portal_ends.iter_mut().find(|(name, _)| *name == c) {
+                        Some((_, ends)) => ends.push(position),
+                        None => portal_ends.push((c, vec![position])),
+                    },
+                    c => return Err(format!("Unknown level cell '{}' at ({}, {})", c, x, y)),
+                }
+            }
+        }
+
+        let mut portals = Vec::new();
+        for (name, ends) in portal_ends {
+            if ends.len() != 2 {
+                return Err(format!("Portal '{}' must appear exactly twice, found {}", name, ends.len()));
+            }
+            portals.push((ends[0], ends[1]));
+        }
+
+        Ok(Level {
+            width: width as u32,
+            height: rows.len() as u32,
+            portals,
+            food_points,
+        })
+    }
+}
+
+// Names as used on the command line and in replays, e.g. `1,1:8,8+3,0:3,9`, or `-` without portals.
+pub fn portals_id(portals: &[(Vec2D, Vec2D)]) -> String {
+    if portals.is_empty() {
+        return String::from("-");
+    }
+    let pairs: Vec<String> = portals.iter()
+        .map(|(first, second)| format!("{},{}:{},{}", first.x, first.y, second.x, second.y))
+        .collect();
+    pairs.join("+")
+}
+
+pub fn portals_by_id(id: &str) -> Option<Vec<(Vec2D, Vec2D)>> {
+    if id == "-" {
+        return Some(Vec::new());
+    }
+    id.split('+').map(portal_by_id).collect()
+}
+
+pub fn portal_by_id(id: &str) -> Option<(Vec2D, Vec2D)> {
+    let mut ends = id.splitn(2, ':').map(|end| {
+        let mut coordinates = end.splitn(2, ',');
+        let x = coordinates.next()?.parse().ok()?;
+        let y = coordinates.next()?.parse().ok()?;
+        Some(Vec2D::new(x, y))
+    });
+    Some((ends.next()??, ends.next()??))
+}
+
+// Portals have to be on the board and not share cells.
+pub fn check_portals(area: &Rectangle, portals: &[(Vec2D, Vec2D)]) -> Result<(), String> {
+    let mut ends: Vec<Vec2D> = Vec::new();
+    for &(first, second) in portals {
+        for end in [first, second].iter() {
+            if !area.contains(end) {
+                return Err(format!("Portal at ({}, {}) is outside of the board", end.x, end.y));
+            }
+            if ends.contains(end) {
+                return Err(format!("Several portals use the cell ({}, {})", end.x, end.y));
+            }
+            ends.push(*end);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_err(content: &str) -> String {
+        Level::parse(content).err().expect("The level should not parse")
+    }
+
+    #[test]
+    fn parses_portals_and_food_points() {
+        let level = Level::parse("a..*\n....\n*..a\n").unwrap();
+        assert_eq!((level.width, level.height), (4, 3));
+        assert_eq!(level.portals, vec![(Vec2D::new(0, 0), Vec2D::new(3, 2))]);
+        assert_eq!(level.food_points, vec![Vec2D::new(3, 0), Vec2D::new(0, 2)]);
+    }
+
+    #[test]
+    fn ignores_blank_lines_and_trailing_spaces() {
+        let level = Level::parse("\n...  \n\n...\n\n").unwrap();
+        assert_eq!((level.width, level.height), (3, 2));
+    }
+
+    #[test]
+    fn rejects_odd_portal_counts() {
+        assert_eq!(parse_err("a..\n...\n..."), "Portal 'a' must appear exactly twice, found 1");
+        assert_eq!(parse_err("a.a\n...\n..a"), "Portal 'a' must appear exactly twice, found 3");
+    }
+
+    #[test]
+    fn rejects_ragged_rows() {
+        assert_eq!(parse_err("...\n..\n..."), "Level rows must all have the same, non-zero length");
+        assert_eq!(parse_err(""), "Level rows must all have the same, non-zero length");
+    }
+
+    #[test]
+    fn rejects_unknown_cells() {
+        assert_eq!(parse_err("...\n.#.\n..."), "Unknown level cell '#' at (1, 1)");
+    }
+
+    #[test]
+    fn portal_ids_round_trip() {
+        let portals = vec![(Vec2D::new(1, 1), Vec2D::new(8, 8)), (Vec2D::new(3, 0), Vec2D::new(3, 9))];
+        assert_eq!(portals_id(&portals), "1,1:8,8+3,0:3,9");
+        assert_eq!(portals_by_id(&portals_id(&portals)), Some(portals));
+        assert_eq!(portals_id(&[]), "-");
+        assert_eq!(portals_by_id("-"), Some(Vec::new()));
+    }
+
+    #[test]
+    fn rejects_invalid_portal_ids() {
+        for id in ["", "1,1", "1,1:8", "1,1:8,x", "1,1:8,8+"].iter() {
+            assert_eq!(portals_by_id(id), None, "{}", id);
+        }
+    }
+
+    #[test]
+    fn portals_stay_on_the_board_without_sharing_cells() {
+        let area = Rectangle::new(0, 0, 9, 9);
+        let portal = (Vec2D::new(1, 1), Vec2D::new(8, 8));
+        assert_eq!(check_portals(&area, &[portal]), Ok(()));
+        assert_eq!(
+            check_portals(&area, &[(Vec2D::new(1, 1), Vec2D::new(10, 8))]),
+            Err(String::from("Portal at (10, 8) is outside of the board"))
+        );
+        assert_eq!(
+            check_portals(&area, &[portal, (Vec2D::new(8, 8), Vec2D::new(5, 5))]),
+            Err(String::from("Several portals use the cell (8, 8)"))
+        );
+    }
+}
@@ -0,0 +1,24 @@
+pub mod achievements;
+pub mod board;
+pub mod camera;
+pub mod cli;
+pub mod console;
+pub mod crash;
+pub mod effects;
+pub mod events;
+pub mod food;
+pub mod game;
+pub mod hazard;
+pub mod level;
+pub mod logging;
+pub mod mode;
+pub mod player;
+pub mod replay;
+pub mod save;
+pub mod software_renderer;
+pub mod spawn;
+pub mod stats;
+pub mod theme;
+pub mod timestep;
+pub mod tui;
+pub mod utils;
@@ -0,0 +1,30 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+// Writes to stderr. Other crates, ggez and its backends among them, only get their warnings through.
+struct Logger;
+
+static LOGGER: Logger = Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Warn || metadata.target().starts_with(env!("CARGO_CRATE_NAME"))
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("[{:<5} {}] {}", record.level(), record.target(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+pub fn init(level: LevelFilter) {
+    log::set_logger(&LOGGER).expect("Failed to set up logging");
+    log::set_max_level(level);
+}
+
+// Names as used on the command line: off, error, warn, info, debug or trace.
+pub fn level_by_id(id: &str) -> Option<LevelFilter> {
+    id.parse().ok()
+}
@@ -1,62 +1,250 @@
-mod player;
-mod utils;
-
-use player::{Player, SpriteType};
-use utils::{Vec2D, Rectangle, Direction};
+use snake::{cli, software_renderer, tui};
+use snake::achievements::{Achievements, AchievementEvent, Turn};
+use snake::camera::Viewport;
+use snake::cli::{Options, Command};
+use snake::console::{self, Console, ConsoleCommand};
+use snake::crash;
+use snake::effects::{EffectsConf, ParticleSystem, ScreenShake, DeathAnimation, Toasts};
+use snake::events::{GameEvent, GameState};
+use snake::food::{FoodConf, FoodPolicy};
+use snake::game::{Game, TickOutcome};
+use snake::hazard::{Hazard, HazardConf};
+use snake::level::{self, Level};
+use snake::logging;
+use snake::mode::{GameMode, Variants};
+use snake::player::{SpriteType, DeathCause, BodyError};
+use snake::replay::Replay;
+use snake::save::SaveState;
+use snake::spawn::{self, SpawnConf, SpawnError};
+use snake::stats::{Profile, StatsEvent};
+use snake::theme::{Theme, Background};
+use snake::timestep::FixedTimestep;
+use snake::utils::{Vec2D, Rectangle, Direction};
 
 use ggez::{graphics, Context, ContextBuilder, GameResult};
-use ggez::conf::{WindowSetup, WindowMode, NumSamples};
-use ggez::event::{self, EventHandler};
+use ggez::conf::{WindowSetup, WindowMode, NumSamples, FullscreenType};
+use ggez::event::{self, EventHandler, KeyCode, KeyMods};
 use ggez::mint::Point2;
 
 use rand::Rng;
 
-use utils::constants::*;
+use directories::ProjectDirs;
+
+use snake::utils::constants::*;
 
 fn main() {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            println!("{}\n\n{}", e, cli::USAGE);
+            return;
+        }
+    };
+    logging::init(options.log_level);
+    install_crash_reporter();
+
+    let (width, height) = options.size;
+    let mut game_conf = SnakeGameConf::new(width, height);
+    game_conf.spawn = options.spawn;
+    game_conf.speed = options.speed;
+    game_conf.mode = options.mode;
+    game_conf.variants = options.variants;
+    game_conf.effects = options.effects;
+    game_conf.food = options.food;
+    game_conf.hazards = options.hazards;
+    game_conf.portals = options.portals;
+    if let Some(path) = &options.level {
+        let level = match Level::load(path) {
+            Ok(level) => level,
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        };
+        game_conf.playing_area = Rectangle::new(0, 0, level.width as i32 - 1, level.height as i32 - 1);
+        game_conf.portals.extend(level.portals);
+        if !level.food_points.is_empty() {
+            game_conf.food.policy = FoodPolicy::Scheduled(level.food_points);
+        }
+    }
+    if let Some(name) = &options.theme {
+        match Theme::by_name(name) {
+            Some(theme) => game_conf.theme = theme,
+            None => {
+                println!("Unknown theme: {}\n\n{}", name, cli::USAGE);
+                return;
+            }
+        }
+    }
+    if let Some(path) = options.background {
+        game_conf.theme.set_background_image(path);
+    }
+
+    if let Err(e) = spawn::check_fits(&game_conf.playing_area, &game_conf.spawn) {
+        println!("{}", e);
+        return;
+    }
+    if let Err(e) = game_conf.food.check(&game_conf.playing_area) {
+        println!("{}", e);
+        return;
+    }
+    if let Err(e) = game_conf.hazards.check() {
+        println!("{}", e);
+        return;
+    }
+    if let Err(e) = level::check_portals(&game_conf.playing_area, &game_conf.portals) {
+        println!("{}", e);
+        return;
+    }
+    // Portals can leave no room for the snake, which only shows when spawning it.
+    let game = match game_conf.new_game(rand::thread_rng().gen()) {
+        Ok(game) => game,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    log::info!("Starting a game with seed {}", game.seed());
+
+    match options.command {
+        Command::Play => (),
+        Command::Tui => {
+            if let Err(e) = tui::run(game, game_conf.speed) {
+                log::error!("Error occured: {}", e);
+            }
+            return;
+        }
+        Command::WatchReplay(replay_path) => {
+            if let Err(e) = tui::watch(&replay_path, game_conf.speed) {
+                log::error!("Error occured: {}", e);
+            }
+            return;
+        }
+        Command::ExportReplay(replay_path, output) => {
+            match software_renderer::export_replay(&replay_path, &output) {
+                Ok(_) => println!("Replay exported to {}", output.display()),
+                Err(e) => log::error!("Error occured: {}", e),
+            }
+            return;
+        }
+    }
+
     let window_setup = WindowSetup::default()
         .title("Snake")
         .samples(NumSamples::Four);
-
-    let game_conf = SnakeGameConf::new(10, 10);
     
-    let (mut ctx, mut event_loop) = ContextBuilder::new("snake", "Corendos")
+    let (mut ctx, mut event_loop) = ContextBuilder::new(GAME_ID, GAME_AUTHOR)
         .window_setup(window_setup)
         .window_mode(game_conf.compute_window_mode())
         .build()
         .expect("Failed to create context");
     
-    let mut my_game = SnakeGame::new(&mut ctx, game_conf);
+    let mut my_game = SnakeGame::new(&mut ctx, game_conf, game);
 
     my_game.load_resources(&mut ctx);
 
     match event::run(&mut ctx, &mut event_loop, &mut my_game) {
-        Ok(_) => println!("Exited clearly."),
-        Err(e) => println!("Error occured: {}", e),
+        Ok(_) => log::info!("Exited clearly."),
+        Err(e) => log::error!("Error occured: {}", e),
     }
 }
 
+// Reacts to the events of the game, registered with `SnakeGame::subscribe`.
+type Subscriber = Box<dyn FnMut(&mut SnakeGame, &mut Context, &GameEvent)>;
+
 struct SnakeGame {
-    player: Player,
+    game: Game,
+    replay: Replay,
     conf: SnakeGameConf,
-    last_time: f32,
+    timestep: FixedTimestep,
     state: GameState,
-    food: Option<Vec2D>,
     resources: SnakeGameResources,
+    particles: ParticleSystem,
+    screen_shake: ScreenShake,
+    death_animation: Option<DeathAnimation>,
+    death_cause: Option<DeathCause>,
+    // Last inconsistency found in the snake body, reported once when it shows up.
+    body_error: Option<BodyError>,
+    viewport: Viewport,
+    fullscreen: bool,
+    paused: bool,
+    debug_overlay: bool,
+    console: Console,
+    console_used: bool,
+    profile: Profile,
+    show_stats: bool,
+    achievements: Achievements,
+    toasts: Toasts,
+    subscribers: Vec<Subscriber>,
 }
 
 impl SnakeGame {
-    pub fn new(_ctx: &mut Context, conf: SnakeGameConf) -> SnakeGame {
-        SnakeGame {
-            player: Player::new(),
+    pub fn new(ctx: &mut Context, conf: SnakeGameConf, game: Game) -> SnakeGame {
+        let viewport = Viewport::new(&conf.playing_area, conf.theme.margin_size());
+        let mut timestep = FixedTimestep::new(MOVE_DELAY);
+        timestep.set_speed(conf.speed);
+        let mut snake_game = SnakeGame {
+            replay: Replay::new(&game),
+            game,
             conf,
-            last_time: 0.0,
-            state: GameState::default(),
-            food: None,
+            timestep,
+            state: if ggez::filesystem::exists(ctx, SAVE_PATH) { GameState::Start } else { GameState::default() },
             resources: SnakeGameResources::default(),
+            particles: ParticleSystem::default(),
+            screen_shake: ScreenShake::default(),
+            death_animation: None,
+            death_cause: None,
+            body_error: None,
+            viewport,
+            fullscreen: false,
+            paused: false,
+            debug_overlay: false,
+            console: Console::default(),
+            console_used: false,
+            profile: Profile::load(&stats_path(ctx)).unwrap_or_else(|e| {
+                log::warn!("Failed to load the statistics: {}", e);
+                Profile::default()
+            }),
+            show_stats: false,
+            achievements: load_achievements(ctx),
+            toasts: Toasts::default(),
+            subscribers: Vec::new(),
+        };
+        snake_game.subscribe(SnakeGame::log_event);
+        snake_game.subscribe(SnakeGame::play_effects);
+        snake_game.subscribe(SnakeGame::record_stats);
+        snake_game.subscribe(SnakeGame::track_achievements);
+        crash::start_run(&snake_game.game, Some(&snake_game.replay));
+        snake_game
+    }
+
+    fn subscribe<F: FnMut(&mut SnakeGame, &mut Context, &GameEvent) + 'static>(&mut self, subscriber: F) {
+        self.subscribers.push(Box::new(subscriber));
+    }
+
+    // The subscribers are taken out while they run, so events sent from one of them reach nobody.
+    fn dispatch(&mut self, ctx: &mut Context, event: &GameEvent) {
+        let mut subscribers = std::mem::take(&mut self.subscribers);
+        for subscriber in subscribers.iter_mut() {
+            subscriber(self, ctx, event);
+        }
+        let added = std::mem::replace(&mut self.subscribers, subscribers);
+        self.subscribers.extend(added);
+    }
+
+    fn dispatch_game_events(&mut self, ctx: &mut Context) {
+        for index in 0..self.game.events().len() {
+            let event = self.game.events()[index];
+            self.dispatch(ctx, &event);
         }
     }
 
+    fn set_state(&mut self, ctx: &mut Context, state: GameState) {
+        let from = self.state;
+        self.state = state;
+        self.dispatch(ctx, &GameEvent::StateChanged { from, to: state });
+    }
+
     fn load_resources(&mut self, ctx: &mut Context) {
         let head_sprite = graphics::Image::new(ctx, "/snake_head.png")
             .expect("Failed to load the specified resource");
@@ -94,61 +282,192 @@ impl SnakeGame {
             .expect("Failed to load the specified resource");
         self.resources.food_sprite = Some(food_sprite);
 
-        let body_mesh = graphics::Mesh::new_rectangle(
+        // The path comes from the command line, a wrong one only costs the image.
+        if let Background::Image(path, color) = &self.conf.theme.background {
+            match graphics::Image::new(ctx, path) {
+                Ok(background_image) => self.resources.background_image = Some(background_image),
+                Err(e) => {
+                    log::error!("Failed to load the background image {}: {}", path, e);
+                    self.conf.theme.background = Background::Color(*color);
+                }
+            }
+        }
+
+        self.resources.board_mesh = self.conf.theme.build_board_mesh(ctx, &self.conf.playing_area)
+            .expect("Failed to build the board mesh");
+
+        let food_mesh = graphics::Mesh::new_rectangle(
             ctx,
             graphics::DrawMode::fill(),
-            graphics::Rect::new(0.0, 0.0, BODY_SIZE as f32, BODY_SIZE as f32),
-            graphics::Color::from_rgb(255, 0, 0)
+            graphics::Rect::new(0.0, 0.0, FOOD_SIZE as f32, FOOD_SIZE as f32),
+            graphics::Color::from_rgb(0, 255, 0)
         ).unwrap();
-        self.resources.body_mesh = Some(body_mesh);
+        self.resources.food_mesh = Some(food_mesh);
 
-        let big_body_mesh = graphics::Mesh::new_rectangle(
+        let particle_mesh = graphics::Mesh::new_rectangle(
             ctx,
             graphics::DrawMode::fill(),
-            graphics::Rect::new(0.0, 0.0, BIG_BODY_SIZE as f32, BIG_BODY_SIZE as f32),
-            graphics::Color::from_rgb(255, 0, 0)
+            graphics::Rect::new(0.0, 0.0, PARTICLE_SIZE as f32, PARTICLE_SIZE as f32),
+            graphics::WHITE
         ).unwrap();
-        self.resources.big_body_mesh = Some(big_body_mesh);
+        self.resources.particle_mesh = Some(particle_mesh);
 
-        let food_mesh = graphics::Mesh::new_rectangle(
+        let highlight_mesh = graphics::Mesh::new_rectangle(
             ctx,
             graphics::DrawMode::fill(),
-            graphics::Rect::new(0.0, 0.0, FOOD_SIZE as f32, FOOD_SIZE as f32),
-            graphics::Color::from_rgb(0, 255, 0)
+            graphics::Rect::new(0.0, 0.0, CELL_SIZE as f32, CELL_SIZE as f32),
+            graphics::Color::from_rgba(255, 0, 0, 110)
         ).unwrap();
-        self.resources.food_mesh = Some(food_mesh);
+        self.resources.highlight_mesh = Some(highlight_mesh);
+
+        let bouncer_mesh = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::fill(),
+            graphics::Rect::new(HAZARD_INSET, HAZARD_INSET, CELL_SIZE as f32 - 2.0 * HAZARD_INSET, CELL_SIZE as f32 - 2.0 * HAZARD_INSET),
+            graphics::Color::from_rgb(200, 40, 40)
+        ).unwrap();
+        self.resources.bouncer_mesh = Some(bouncer_mesh);
+
+        let wall_mesh = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::fill(),
+            graphics::Rect::new(0.0, 0.0, CELL_SIZE as f32, CELL_SIZE as f32),
+            graphics::Color::from_rgb(70, 70, 70)
+        ).unwrap();
+        self.resources.wall_mesh = Some(wall_mesh);
+
+        let half_cell = CELL_SIZE as f32 / 2.0;
+        let portal_mesh = graphics::Mesh::new_circle(
+            ctx,
+            graphics::DrawMode::fill(),
+            Point2 { x: half_cell, y: half_cell },
+            half_cell - HAZARD_INSET,
+            0.5,
+            graphics::WHITE
+        ).unwrap();
+        self.resources.portal_mesh = Some(portal_mesh);
     }
 
     fn handle_input(&mut self, ctx: &Context) {
         if ggez::input::keyboard::is_key_pressed(ctx, event::KeyCode::W) {
-            self.player.wanted_direction = Some(Direction::Up);
+            self.game.player.wanted_direction = Some(Direction::Up);
         }
         if ggez::input::keyboard::is_key_pressed(ctx, event::KeyCode::S) {
-            self.player.wanted_direction = Some(Direction::Down);
+            self.game.player.wanted_direction = Some(Direction::Down);
         }
         if ggez::input::keyboard::is_key_pressed(ctx, event::KeyCode::A) {
-            self.player.wanted_direction = Some(Direction::Left);
+            self.game.player.wanted_direction = Some(Direction::Left);
         }
         if ggez::input::keyboard::is_key_pressed(ctx, event::KeyCode::D) {
-            self.player.wanted_direction = Some(Direction::Right);
+            self.game.player.wanted_direction = Some(Direction::Right);
         }
     }
 
     fn draw_playing(&self, ctx: &mut Context) {
-        //self.player.print_debug();
-
+        self.draw_portals(ctx);
+        self.draw_hazards(ctx);
         self.draw_food(ctx);
         self.draw_snake(ctx);
+        self.draw_particles(ctx);
+    }
+
+    fn draw_board(&self, ctx: &mut Context) {
+        if let Some(background_image) = &self.resources.background_image {
+            let board_rect = self.viewport.board_rect();
+            let draw_param = graphics::DrawParam::new().scale([
+                board_rect.w / background_image.width() as f32,
+                board_rect.h / background_image.height() as f32,
+            ]);
+            graphics::draw(ctx, background_image, draw_param).unwrap();
+        }
+        if let Some(board_mesh) = &self.resources.board_mesh {
+            graphics::draw(ctx, board_mesh, graphics::DrawParam::new()).unwrap();
+        }
+    }
+
+    fn draw_fatal_cell(&self, ctx: &mut Context) {
+        if let Some(position) = self.death_cause.as_ref().and_then(|cause| self.game.fatal_cell(cause)) {
+            let cell_position = Vec2D::new(
+                position.x * CELL_SIZE as i32,
+                position.y * CELL_SIZE as i32
+            );
+            graphics::draw(ctx, self.resources.highlight_mesh.as_ref().unwrap(), graphics::DrawParam::new().dest(cell_position))
+                .unwrap();
+        }
     }
 
     fn draw_game_over(&self, ctx: &mut Context) {
-        let text = graphics::Text::new("Game Over");
+        let mut details = vec![self.game.mode.to_string()];
+        if let Some(cause) = self.death_cause {
+            details.push(cause.to_string());
+        }
+        details.push(format!("Score: {}", self.game.score));
+        self.draw_message(ctx, "Game Over", Some(details.join("\n")));
+    }
+
+    fn draw_countdown(&self, ctx: &mut Context) {
+        let time_left = match self.game.mode.rules().time_left(self.game.ticks()) {
+            Some(time_left) => time_left / self.timestep.speed(),
+            None => return,
+        };
+        let scale = self.viewport.scale();
+        let seconds = time_left.ceil() as u32;
+        let text = graphics::Text::new(graphics::TextFragment::new(format!("{}:{:02}", seconds / 60, seconds % 60))
+            .scale(graphics::Scale::uniform(DEATH_CAUSE_FONT_SIZE * scale)));
         let (text_width, text_height) = text.dimensions(ctx);
-        let (window_width, window_height) = graphics::drawable_size(ctx);
-        let text_position = Vec2D::new(
-            ((window_width as u32 - text_width) / 2) as i32,
-            ((window_height as u32 - text_height) / 2) as i32
+        let (window_width, _) = self.viewport.window_size();
+        let padding = GAME_OVER_PADDING * scale;
+        let backdrop_rect = graphics::Rect::new(
+            window_width - text_width as f32 - 3.0 * padding,
+            padding,
+            text_width as f32 + 2.0 * padding,
+            text_height as f32 + 2.0 * padding
         );
+        draw_backdrop(ctx, backdrop_rect, graphics::Color::from_rgba(255, 255, 255, 200));
+
+        let color = if time_left <= COUNTDOWN_WARNING {
+            graphics::Color::from_rgb(220, 30, 30)
+        } else {
+            graphics::Color::from_rgb(0, 0, 0)
+        };
+        let position = Point2 {
+            x: backdrop_rect.x + padding,
+            y: backdrop_rect.y + padding,
+        };
+        graphics::draw(ctx, &text, graphics::DrawParam::new().dest(position).color(color)).unwrap();
+    }
+
+    fn draw_start(&self, ctx: &mut Context) {
+        self.draw_message(ctx, "Snake", Some(String::from("C: continue the saved game\nN: new game")));
+    }
+
+    fn draw_message(&self, ctx: &mut Context, title: &str, details: Option<String>) {
+        let scale = self.viewport.scale();
+        let mut text = graphics::Text::new(graphics::TextFragment::new(title)
+            .scale(graphics::Scale::uniform(GAME_OVER_FONT_SIZE * scale)));
+        if let Some(details) = details {
+            text.add(graphics::TextFragment::new(format!("\n{}", details))
+                .scale(graphics::Scale::uniform(DEATH_CAUSE_FONT_SIZE * scale)));
+        }
+
+        // Once centred, the dimensions reach the right edge of the text instead of giving its width.
+        let (text_width, text_height) = text.dimensions(ctx);
+        let (window_width, window_height) = self.viewport.window_size();
+        text.set_bounds(Point2 { x: window_width, y: f32::INFINITY }, graphics::Align::Center);
+
+        let padding = GAME_OVER_PADDING * scale;
+        let backdrop_rect = graphics::Rect::new(
+            (window_width - text_width as f32) / 2.0 - padding,
+            (window_height - text_height as f32) / 2.0 - padding,
+            text_width as f32 + 2.0 * padding,
+            text_height as f32 + 2.0 * padding
+        );
+        draw_backdrop(ctx, backdrop_rect, graphics::Color::from_rgba(255, 255, 255, 200));
+
+        let text_position = Point2 {
+            x: 0.0,
+            y: (window_height - text_height as f32) / 2.0,
+        };
         graphics::draw(
             ctx,
             &text,
@@ -156,8 +475,116 @@ impl SnakeGame {
         ).unwrap();
     }
 
+    // Toasts sit at the top of the window, over the board.
+    fn draw_toast(&self, ctx: &mut Context) {
+        let (content, alpha) = match self.toasts.current() {
+            Some(toast) => toast,
+            None => return,
+        };
+        let scale = self.viewport.scale();
+        let mut text = graphics::Text::new(graphics::TextFragment::new(content)
+            .scale(graphics::Scale::uniform(TOAST_FONT_SIZE * scale)));
+        // Measured before centring, like in `draw_message`.
+        let (text_width, text_height) = text.dimensions(ctx);
+        let (window_width, _) = self.viewport.window_size();
+        text.set_bounds(Point2 { x: window_width, y: f32::INFINITY }, graphics::Align::Center);
+
+        let padding = GAME_OVER_PADDING * scale;
+        let backdrop_rect = graphics::Rect::new(
+            (window_width - text_width as f32) / 2.0 - padding,
+            padding,
+            text_width as f32 + 2.0 * padding,
+            text_height as f32 + 2.0 * padding
+        );
+        draw_backdrop(ctx, backdrop_rect, graphics::Color::new(0.0, 0.0, 0.0, 0.8 * alpha));
+        graphics::draw(
+            ctx,
+            &text,
+            graphics::DrawParam::new()
+                .dest(Point2 { x: 0.0, y: 2.0 * padding })
+                .color(graphics::Color::new(1.0, 1.0, 1.0, alpha))
+        ).unwrap();
+    }
+
+    fn draw_debug_overlay(&self, ctx: &mut Context) {
+        let player = &self.game.player;
+        let head = player.body[0].position;
+        let cell_count = (self.game.playing_area.width() * self.game.playing_area.height()) as usize;
+        let mut lines = vec![
+            format!("FPS: {:.0}", ggez::timer::fps(ctx)),
+            format!("Mode: {}", self.game.mode),
+            format!("Tick: {}", self.game.ticks()),
+            format!("Score: {}", self.game.score),
+            format!("Head: ({}, {})", head.x, head.y),
+            format!("Direction: {:?}", player.direction),
+            format!("Wanted direction: {:?}", player.wanted_direction),
+            format!("Length: {} ({:+} pending)", player.body.len(), player.pending_growth),
+            format!("Occupied cells: {}", cell_count - self.game.board.free_cell_count()),
+            format!("Speed: {}x", self.timestep.speed()),
+        ];
+        if self.paused {
+            lines.push(String::from("Paused"));
+        }
+
+        let text = graphics::Text::new(graphics::TextFragment::new(lines.join("\n"))
+            .scale(graphics::Scale::uniform(DEBUG_FONT_SIZE)));
+        let (text_width, text_height) = text.dimensions(ctx);
+        let backdrop_rect = graphics::Rect::new(
+            0.0,
+            0.0,
+            text_width as f32 + 2.0 * DEBUG_PADDING,
+            text_height as f32 + 2.0 * DEBUG_PADDING
+        );
+        draw_backdrop(ctx, backdrop_rect, graphics::Color::from_rgba(0, 0, 0, 160));
+        graphics::draw(ctx, &text, graphics::DrawParam::new().dest(Point2 { x: DEBUG_PADDING, y: DEBUG_PADDING }))
+            .unwrap();
+    }
+
+    // One arrow per segment pointing toward the head, the head points where it moves.
+    fn draw_orientation_arrows(&self, ctx: &mut Context) {
+        let mut builder = graphics::MeshBuilder::new();
+        let length = CELL_SIZE as f32 / 3.0;
+        for body_part in self.game.player.body.iter() {
+            let direction = match body_part.orientation {
+                (Some(direction), _) | (None, Some(direction)) => direction,
+                (None, None) => continue,
+            };
+            let forward = Point2::from(direction.to_vec());
+            let side = Point2::from(direction.rotate_right().to_vec());
+            let center = cell_center(&body_part.position);
+            let point = |along: f32, across: f32| Point2 {
+                x: center.x + forward.x * along + side.x * across,
+                y: center.y + forward.y * along + side.y * across,
+            };
+
+            let color = graphics::Color::from_rgb(255, 0, 255);
+            builder.line(&[point(-length, 0.0), point(length, 0.0)], DEBUG_ARROW_WIDTH, color).unwrap();
+            builder.polygon(
+                graphics::DrawMode::fill(),
+                &[point(length, 0.0), point(length / 2.0, length / 3.0), point(length / 2.0, -length / 3.0)],
+                color
+            ).unwrap();
+        }
+        let mesh = builder.build(ctx).unwrap();
+        graphics::draw(ctx, &mesh, graphics::DrawParam::new()).unwrap();
+    }
+
+    fn draw_console(&self, ctx: &mut Context) {
+        let (window_width, window_height) = self.viewport.window_size();
+        let content = format!("{}\n> {}_", self.console.output, self.console.input);
+        let text = graphics::Text::new(graphics::TextFragment::new(content)
+            .scale(graphics::Scale::uniform(DEBUG_FONT_SIZE)));
+        let text_height = text.height(ctx) as f32;
+        let top = window_height - text_height - 2.0 * DEBUG_PADDING;
+
+        draw_backdrop(ctx, graphics::Rect::new(0.0, top, window_width, window_height - top), graphics::Color::from_rgba(0, 0, 0, 200));
+        graphics::draw(ctx, &text, graphics::DrawParam::new().dest(Point2 { x: DEBUG_PADDING, y: top + DEBUG_PADDING }))
+            .unwrap();
+    }
+
     fn draw_food(&self, ctx: &mut Context) {
-        if let Some(position) = &self.food {
+        for food in self.game.food.iter() {
+            let position = food.position;
             let food_position = Vec2D::new(
                 position.x * CELL_SIZE as i32,
                 position.y * CELL_SIZE as i32
@@ -167,9 +594,58 @@ impl SnakeGame {
         }
     }
 
+    // The portal mesh is white, each pair is tinted with its own color.
+    fn draw_portals(&self, ctx: &mut Context) {
+        for (i, (first, second)) in self.game.board.portals().iter().enumerate() {
+            let (r, g, b) = PORTAL_COLORS[i % PORTAL_COLORS.len()];
+            for position in [first, second].iter() {
+                let cell_position = Vec2D::new(
+                    position.x * CELL_SIZE as i32,
+                    position.y * CELL_SIZE as i32
+                );
+                let draw_param = graphics::DrawParam::new()
+                    .dest(cell_position)
+                    .color(graphics::Color::from_rgb(r, g, b));
+                graphics::draw(ctx, self.resources.portal_mesh.as_ref().unwrap(), draw_param).unwrap();
+            }
+        }
+    }
+
+    fn draw_hazards(&self, ctx: &mut Context) {
+        for hazard in self.game.hazards.iter() {
+            let mesh = match hazard {
+                Hazard::Bouncer { .. } => &self.resources.bouncer_mesh,
+                Hazard::TemporaryWall { is_up: true, .. } => &self.resources.wall_mesh,
+                Hazard::TemporaryWall { is_up: false, .. } => continue,
+            };
+            let position = hazard.position();
+            let cell_position = Vec2D::new(
+                position.x * CELL_SIZE as i32,
+                position.y * CELL_SIZE as i32
+            );
+            graphics::draw(ctx, mesh.as_ref().unwrap(), graphics::DrawParam::new().dest(cell_position))
+                .unwrap();
+        }
+    }
+
+    fn draw_particles(&self, ctx: &mut Context) {
+        self.particles.draw(ctx, self.resources.particle_mesh.as_ref().unwrap()).unwrap();
+    }
+
     fn draw_snake(&self, ctx: &mut Context) {
-        for body_part in self.player.body.iter() {
-            let (sprite_type, rotation) = body_part.get_sprite_and_rotation().unwrap();
+        let body_length = self.game.player.body.len();
+        for (i, body_part) in self.game.player.body.iter().enumerate() {
+            if let Some(death_animation) = &self.death_animation {
+                if !death_animation.is_visible(body_length - 1 - i) {
+                    continue;
+                }
+            }
+
+            // Broken segments are left out, `step` reports them.
+            let (sprite_type, rotation) = match body_part.get_sprite_and_rotation() {
+                Ok(sprite_and_rotation) => sprite_and_rotation,
+                Err(_) => continue,
+            };
 
             let draw_param = graphics::DrawParam::new()
                 .rotation(rotation)
@@ -192,101 +668,440 @@ impl SnakeGame {
                 (SpriteType::Right, true) => graphics::draw(ctx, self.resources.body_right_sprite_big.as_ref().unwrap(), draw_param),
                 (SpriteType::Right, false) => graphics::draw(ctx, self.resources.body_right_sprite.as_ref().unwrap(), draw_param),
             }.unwrap();
-            /*if body_part.is_big {
-                let body_position = Vec2D {
-                    x: body_part.position.x * CELL_SIZE as i32 + BIG_OFFSET as i32,
-                    y: body_part.position.y * CELL_SIZE as i32 + BIG_OFFSET as i32,
-                };
-                let draw_param = graphics::DrawParam::new().dest(body_position);
-                graphics::draw(ctx, self.resources.big_body_mesh.as_ref().unwrap(), draw_param).unwrap();
-            } else {
-                let body_position = Vec2D {
-                    x: body_part.position.x * CELL_SIZE as i32 + OFFSET as i32,
-                    y: body_part.position.y * CELL_SIZE as i32 + OFFSET as i32,
-                };
-                let draw_param = graphics::DrawParam::new().dest(body_position);
-                graphics::draw(ctx, self.resources.body_mesh.as_ref().unwrap(), draw_param).unwrap();
-            }*/
         }
     }
 
     fn update_playing(&mut self, ctx: &mut Context) {
-        if self.food.is_none() {
-            self.generate_food(ctx);
+        if !self.console.is_open {
+            self.handle_input(ctx);
+        }
+        if self.paused || self.show_stats {
+            return;
         }
-        self.handle_input(ctx);
 
-        self.last_time += ggez::timer::delta(ctx).as_secs_f32();
+        self.timestep.advance(ggez::timer::delta(ctx).as_secs_f32());
 
-        if self.last_time > MOVE_DELAY {
-            self.last_time -= MOVE_DELAY;
+        while self.timestep.next_step() {
+            if !self.step(ctx) {
+                break;
+            }
+        }
+    }
 
-            if let Some(direction) = self.player.wanted_direction {
-                match direction {
-                    Direction::Up => if self.player.direction != Direction::Down { self.player.direction = direction; }
-                    Direction::Down => if self.player.direction != Direction::Up { self.player.direction = direction; }
-                    Direction::Left => if self.player.direction != Direction::Right { self.player.direction = direction; }
-                    Direction::Right => if self.player.direction != Direction::Left { self.player.direction = direction; }
-                }
+    // Runs one game tick, returns false once the snake died.
+    fn step(&mut self, ctx: &mut Context) -> bool {
+        self.replay.record(self.game.player.wanted_direction);
+        crash::record(self.game.player.wanted_direction);
+        let outcome = self.game.tick();
+        self.check_body();
+        self.dispatch_game_events(ctx);
+
+        if let TickOutcome::Died(cause) = outcome {
+            self.on_death(ctx, cause);
+            return false;
+        }
+        true
+    }
+
+    // Drawing skips broken segments, this is where they get reported.
+    fn check_body(&mut self) {
+        let body_error = self.game.player.validate_body(&self.game.board).err();
+        if let Some(e) = body_error.filter(|_| body_error != self.body_error) {
+            log::warn!("Invalid snake body at tick {}: {}", self.game.ticks(), e);
+        }
+        self.body_error = body_error;
+    }
+
+    fn log_event(&mut self, _ctx: &mut Context, event: &GameEvent) {
+        match event {
+            GameEvent::Ticked { .. } => log::trace!("{:?}", event),
+            _ => log::debug!("{:?}", event),
+        }
+    }
+
+    fn track_achievements(&mut self, ctx: &mut Context, event: &GameEvent) {
+        let area = &self.game.playing_area;
+        let event = match *event {
+            GameEvent::Turned { from, to } if to == from.rotate_left() => AchievementEvent::Turned(Turn::Left),
+            GameEvent::Turned { .. } => AchievementEvent::Turned(Turn::Right),
+            GameEvent::Ate { .. } => AchievementEvent::Ate,
+            GameEvent::Ticked { ticks } => AchievementEvent::Moved {
+                ticks,
+                length: self.game.player.body.len(),
+                cell_count: (area.width() * area.height()) as usize,
+            },
+            GameEvent::Died { .. } => AchievementEvent::Died,
+            GameEvent::StateChanged { to: GameState::Playing, .. } => {
+                self.achievements.start_run();
+                return;
             }
+            _ => return,
+        };
+        self.notify_achievements(ctx, event);
+    }
 
-            if self.player.can_move(&self.conf.playing_area) {
-                self.player.r#move();
-                if let Some(position) = &self.food {
-                    if self.player.body[0].position == *position {
-                        self.player.eat();
-                        self.generate_food(ctx);
-                    }
-                }
-            } else {
-                self.state = GameState::GameOver;
+    // Runs touched by the console do not unlock anything.
+    fn notify_achievements(&mut self, ctx: &Context, event: AchievementEvent) {
+        if self.console_used {
+            return;
+        }
+        let unlocked = self.achievements.handle(&event);
+        for achievement in unlocked.iter() {
+            self.toasts.push(format!("Achievement unlocked: {}\n{}", achievement.name, achievement.description));
+        }
+        if !unlocked.is_empty() {
+            let path = ggez::filesystem::user_config_dir(ctx).join(UNLOCKED_ACHIEVEMENTS_FILE);
+            if let Err(e) = self.achievements.save_unlocked(&path) {
+                log::error!("Failed to save the achievements: {}", e);
+            }
+        }
+    }
+
+    fn update_dying(&mut self, ctx: &mut Context) {
+        let dt = ggez::timer::delta(ctx).as_secs_f32();
+        let body_length = self.game.player.body.len();
+
+        let death_animation = self.death_animation.as_mut().unwrap();
+        let scattered = death_animation.update(dt);
+        let is_finished = death_animation.is_finished();
+
+        if self.conf.effects.particles {
+            for index_from_tail in scattered {
+                let position = self.game.player.body[body_length - 1 - index_from_tail].position;
+                self.particles.burst(cell_center(&position), DEATH_PARTICLE_COUNT, graphics::Color::from_rgb(90, 160, 40));
             }
         }
 
+        if is_finished {
+            self.death_animation = None;
+            self.set_state(ctx, GameState::GameOver);
+        }
+    }
+
+    fn update_start(&mut self, ctx: &mut Context) {
+        if ggez::input::keyboard::is_key_pressed(ctx, event::KeyCode::C) {
+            if let Err(e) = self.load_save(ctx) {
+                log::warn!("Failed to load the saved game: {}", e);
+            }
+            self.set_state(ctx, GameState::Playing);
+        } else if ggez::input::keyboard::is_key_pressed(ctx, event::KeyCode::N) {
+            self.delete_save(ctx);
+            self.set_state(ctx, GameState::Playing);
+        }
+    }
+
+    fn load_save(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let save = SaveState::read(ggez::filesystem::open(ctx, SAVE_PATH)?)?;
+        let area = &save.game.playing_area;
+        if *area != self.conf.playing_area {
+            return Err(ggez::GameError::ResourceLoadError(
+                format!("The game was saved on a {}x{} board", area.width(), area.height())
+            ));
+        }
+
+        self.game = save.game;
+        self.console_used = save.replay.is_none();
+        self.replay = save.replay
+            .unwrap_or_else(|| Replay::new(&self.game));
+        self.timestep.set_accumulator(save.timer_accumulator);
+        crash::start_run(&self.game, if self.console_used { None } else { Some(&self.replay) });
+        log::info!("Resumed a game at tick {}", self.game.ticks());
+        Ok(())
+    }
+
+    // Only a run still in progress is worth resuming, dead snakes are not saved.
+    fn autosave(&self, ctx: &mut Context) {
+        if let GameState::Playing = self.state {
+            let save = SaveState {
+                game: self.game.clone(),
+                replay: if self.console_used { None } else { Some(self.replay.clone()) },
+                timer_accumulator: self.timestep.accumulator(),
+            };
+            let written = ggez::filesystem::create(ctx, SAVE_PATH)
+                .map_err(|e| e.to_string())
+                .and_then(|file| save.write(file).map_err(|e| e.to_string()));
+            if let Err(e) = written {
+                log::error!("Failed to save the game: {}", e);
+            }
+        }
+    }
+
+    fn delete_save(&self, ctx: &mut Context) {
+        if ggez::filesystem::exists(ctx, SAVE_PATH) {
+            if let Err(e) = ggez::filesystem::delete(ctx, SAVE_PATH) {
+                log::error!("Failed to delete the save file: {}", e);
+            }
+        }
     }
 
     fn update_game_over(&mut self, ctx: &mut Context) {
-        if ggez::input::keyboard::is_key_pressed(ctx, event::KeyCode::Space) {
-            self.state = GameState::Playing;
-            self.player.reset();
-            self.last_time = 0.0;
+        if !self.console.is_open && ggez::input::keyboard::is_key_pressed(ctx, event::KeyCode::Space) {
+            self.restart(ctx, rand::thread_rng().gen());
+        }
+    }
+
+    fn restart(&mut self, ctx: &mut Context, seed: u64) {
+        log::info!("Starting a game with seed {}", seed);
+        self.game.reset(seed);
+        self.replay = Replay::new(&self.game);
+        crash::start_run(&self.game, Some(&self.replay));
+        self.death_cause = None;
+        self.death_animation = None;
+        self.console_used = false;
+        self.timestep.reset();
+        self.particles.clear();
+        self.screen_shake.stop();
+        self.set_state(ctx, GameState::Playing);
+        self.dispatch_game_events(ctx);
+    }
+
+    fn run_console_command(&mut self, ctx: &mut Context, command: ConsoleCommand) -> String {
+        match command {
+            ConsoleCommand::Grow(amount) => {
+                self.game.player.grow(amount);
+                self.console_used = true;
+                crash::start_run(&self.game, None);
+                format!("Growing by {}", amount)
+            }
+            ConsoleCommand::SpawnFood(position) => {
+                if self.game.place_food(position) {
+                    self.console_used = true;
+                    crash::start_run(&self.game, None);
+                    format!("Food moved to ({}, {})", position.x, position.y)
+                } else {
+                    format!("Cell ({}, {}) is not free", position.x, position.y)
+                }
+            }
+            ConsoleCommand::Speed(speed) => {
+                if let Some(speed) = speed {
+                    self.timestep.set_speed(speed);
+                }
+                format!("Speed {}x", self.timestep.speed())
+            }
+            ConsoleCommand::Seed(None) => format!("Seed {}", self.game.seed()),
+            ConsoleCommand::Seed(Some(seed)) => {
+                self.restart(ctx, seed);
+                format!("Restarted with seed {}", seed)
+            }
+            ConsoleCommand::Pause => {
+                self.paused = !self.paused;
+                String::from(if self.paused { "Paused" } else { "Resumed" })
+            }
+            ConsoleCommand::Step(count) => {
+                if !self.paused {
+                    return String::from("Pause the game before stepping");
+                }
+                let mut steps = 0;
+                while steps < count && matches!(self.state, GameState::Playing) {
+                    self.step(ctx);
+                    steps += 1;
+                }
+                format!("Stepped {} ticks, now at tick {}", steps, self.game.ticks())
+            }
+            ConsoleCommand::Help => String::from(console::HELP),
+        }
+    }
+
+    fn update_effects(&mut self, ctx: &mut Context) {
+        let dt = ggez::timer::delta(ctx).as_secs_f32();
+        self.particles.update(dt);
+        self.screen_shake.update(dt);
+        self.toasts.update(dt);
+    }
+
+    fn toggle_fullscreen(&mut self, ctx: &mut Context) {
+        self.fullscreen = !self.fullscreen;
+        let fullscreen_type = if self.fullscreen {
+            FullscreenType::Desktop
+        } else {
+            FullscreenType::Windowed
+        };
+        graphics::set_fullscreen(ctx, fullscreen_type).expect("Failed to change fullscreen mode");
+    }
+
+    fn play_effects(&mut self, _ctx: &mut Context, event: &GameEvent) {
+        match *event {
+            GameEvent::Ate { position, .. } => self.on_food_eaten(position),
+            GameEvent::TailCut { position, .. } => self.on_tail_cut(position),
+            _ => (),
         }
     }
 
-    fn generate_food(&mut self, _ctx: &mut Context) {
-        let mut rng = rand::thread_rng();
-        let mut x = rng.gen_range(self.conf.playing_area.min.x, self.conf.playing_area.max.x);
-        let mut y = rng.gen_range(self.conf.playing_area.min.y, self.conf.playing_area.max.y);
+    fn on_food_eaten(&mut self, position: Vec2D) {
+        if self.conf.effects.particles {
+            self.particles.burst(cell_center(&position), FOOD_PARTICLE_COUNT, graphics::Color::from_rgb(220, 50, 50));
+        }
+        if self.conf.effects.screen_shake {
+            self.screen_shake.start(EAT_SHAKE_INTENSITY, EAT_SHAKE_DURATION);
+        }
+    }
 
-        let mut food_position = Vec2D::new(x, y);
+    fn on_tail_cut(&mut self, position: Vec2D) {
+        if self.conf.effects.particles {
+            self.particles.burst(cell_center(&position), FOOD_PARTICLE_COUNT, graphics::Color::from_rgb(90, 160, 40));
+        }
+    }
 
-        while self.player.body_positions.contains(&food_position) {
-            x = rng.gen_range(self.conf.playing_area.min.x, self.conf.playing_area.max.x);
-            y = rng.gen_range(self.conf.playing_area.min.y, self.conf.playing_area.max.y);
+    fn on_death(&mut self, ctx: &mut Context, cause: DeathCause) {
+        self.death_cause = Some(cause);
+        self.save_replay(ctx);
+        self.delete_save(ctx);
 
-            food_position = Vec2D::new(x, y);
+        if self.conf.effects.screen_shake {
+            self.screen_shake.start(DEATH_SHAKE_INTENSITY, DEATH_SHAKE_DURATION);
         }
 
-        self.food = Some(food_position);
+        if self.conf.effects.death_animation {
+            self.death_animation = Some(DeathAnimation::new(self.game.player.body.len(), DEATH_ANIMATION_DURATION));
+            self.set_state(ctx, GameState::Dying);
+        } else {
+            self.set_state(ctx, GameState::GameOver);
+        }
+    }
+
+    // Runs touched by the console do not count.
+    fn record_stats(&mut self, ctx: &mut Context, event: &GameEvent) {
+        if self.console_used {
+            return;
+        }
+        match *event {
+            GameEvent::Ate { .. } => {
+                let player = &self.game.player;
+                let length = player.body.len() + player.pending_growth.max(0) as usize;
+                self.profile.record(&StatsEvent::Ate { length });
+            }
+            GameEvent::Died { cause } => {
+                self.profile.record(&StatsEvent::Ended {
+                    mode: self.game.mode,
+                    score: self.game.score,
+                    ticks: self.game.ticks(),
+                    food: self.game.food_eaten(),
+                    length: self.game.player.body.len(),
+                    cause,
+                });
+                self.save_stats(ctx);
+            }
+            _ => (),
+        }
     }
+
+    fn save_stats(&self, ctx: &Context) {
+        if let Err(e) = self.profile.save(&stats_path(ctx)) {
+            log::error!("Failed to save the statistics: {}", e);
+        }
+    }
+
+    // Console changes are not recorded, a replay of a game touched by the console would diverge.
+    fn save_replay(&self, ctx: &mut Context) {
+        if self.console_used {
+            return;
+        }
+        let written = ggez::filesystem::create(ctx, LAST_REPLAY_PATH)
+            .map_err(|e| e.to_string())
+            .and_then(|file| self.replay.write(file).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            log::error!("Failed to save the replay: {}", e);
+        }
+    }
+}
+
+// Missing or broken definitions only disable achievements.
+fn load_achievements(ctx: &mut Context) -> Achievements {
+    let achievements = ggez::filesystem::open(ctx, ACHIEVEMENTS_PATH)
+        .map_err(|e| e.to_string())
+        .and_then(|file| Achievements::new(file).map_err(|e| e.to_string()));
+    let mut achievements = match achievements {
+        Ok(achievements) => achievements,
+        Err(e) => {
+            log::warn!("Failed to load the achievements: {}", e);
+            return Achievements::default();
+        }
+    };
+    let path = ggez::filesystem::user_config_dir(ctx).join(UNLOCKED_ACHIEVEMENTS_FILE);
+    if let Err(e) = achievements.load_unlocked(&path) {
+        log::warn!("Failed to load the unlocked achievements: {}", e);
+    }
+    achievements
+}
+
+// Reports go to the ggez user data directory, which needs no context to be found.
+fn install_crash_reporter() {
+    match ProjectDirs::from("", GAME_AUTHOR, GAME_ID) {
+        Some(dirs) => {
+            let command_line: Vec<String> = std::env::args().collect();
+            crash::install(dirs.data_local_dir().to_path_buf(), command_line.join(" "));
+        }
+        None => log::warn!("No user data directory, crash reports are disabled"),
+    }
+}
+
+fn stats_path(ctx: &Context) -> std::path::PathBuf {
+    ggez::filesystem::user_config_dir(ctx).join(STATS_FILE)
+}
+
+fn cell_center(position: &Vec2D) -> Point2<f32> {
+    Point2 {
+        x: (position.x * CELL_SIZE as i32) as f32 + (CELL_SIZE / 2) as f32,
+        y: (position.y * CELL_SIZE as i32) as f32 + (CELL_SIZE / 2) as f32,
+    }
+}
+
+fn draw_backdrop(ctx: &mut Context, rect: graphics::Rect, color: graphics::Color) {
+    let backdrop = graphics::Mesh::new_rectangle(ctx, graphics::DrawMode::fill(), rect, color).unwrap();
+    graphics::draw(ctx, &backdrop, graphics::DrawParam::new()).unwrap();
 }
 
 struct SnakeGameConf {
     playing_area: Rectangle,
+    spawn: SpawnConf,
+    food: FoodConf,
+    hazards: HazardConf,
+    portals: Vec<(Vec2D, Vec2D)>,
+    mode: GameMode,
+    variants: Variants,
+    speed: f32,
+    effects: EffectsConf,
+    theme: Theme,
 }
 
 impl SnakeGameConf {
     fn new(width: u32, height: u32) -> SnakeGameConf {
         SnakeGameConf {
-            playing_area: Rectangle::new(0, 0, width as i32 - 1, height as i32 - 1)
+            playing_area: Rectangle::new(0, 0, width as i32 - 1, height as i32 - 1),
+            spawn: SpawnConf::default(),
+            food: FoodConf::default(),
+            hazards: HazardConf::default(),
+            portals: Vec::new(),
+            mode: GameMode::default(),
+            variants: Variants::default(),
+            speed: 1.0,
+            effects: EffectsConf::default(),
+            theme: Theme::default(),
         }
     }
 
+    fn new_game(&self, seed: u64) -> Result<Game, SpawnError> {
+        let mut game = Game::new(
+            self.playing_area.clone(),
+            self.spawn.clone(),
+            self.food.clone(),
+            self.hazards.clone(),
+            self.portals.clone(),
+            self.mode,
+            seed
+        )?;
+        game.variants = self.variants;
+        Ok(game)
+    }
+
     fn compute_window_mode(&self) -> WindowMode {
-        let width = self.playing_area.width() * CELL_SIZE;
-        let height = self.playing_area.height() * CELL_SIZE;
+        let margin = 2 * self.theme.margin * CELL_SIZE;
+        let width = self.playing_area.width() * CELL_SIZE + margin;
+        let height = self.playing_area.height() * CELL_SIZE + margin;
         WindowMode::default()
             .dimensions(width as f32, height as f32)
+            .min_dimensions((width / 2) as f32, (height / 2) as f32)
+            .resizable(true)
     }
 }
 
@@ -300,9 +1115,14 @@ struct SnakeGameResources {
     body_left_sprite_big: Option<graphics::Image>,
     tail_sprite: Option<graphics::Image>,
     food_sprite: Option<graphics::Image>,
-    body_mesh: Option<graphics::Mesh>,
-    big_body_mesh: Option<graphics::Mesh>,
     food_mesh: Option<graphics::Mesh>,
+    board_mesh: Option<graphics::Mesh>,
+    background_image: Option<graphics::Image>,
+    particle_mesh: Option<graphics::Mesh>,
+    highlight_mesh: Option<graphics::Mesh>,
+    bouncer_mesh: Option<graphics::Mesh>,
+    wall_mesh: Option<graphics::Mesh>,
+    portal_mesh: Option<graphics::Mesh>,
 }
 
 impl SnakeGameResources {
@@ -317,41 +1137,122 @@ impl SnakeGameResources {
             body_left_sprite_big: None,
             tail_sprite: None,
             food_sprite: None,
-            body_mesh: None,
-            big_body_mesh: None,
             food_mesh: None,
+            board_mesh: None,
+            background_image: None,
+            particle_mesh: None,
+            highlight_mesh: None,
+            bouncer_mesh: None,
+            wall_mesh: None,
+            portal_mesh: None,
         }
     }
 }
 
 impl EventHandler for SnakeGame {
     fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
+        self.update_effects(ctx);
+
         match self.state {
+            GameState::Start => self.update_start(ctx),
             GameState::GameOver => self.update_game_over(ctx),
             GameState::Playing => self.update_playing(ctx),
+            GameState::Dying => self.update_dying(ctx),
         }
         Ok(())        
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
-        graphics::clear(ctx, graphics::WHITE);
+        graphics::clear(ctx, self.conf.theme.surround_color);
+
+        let board_transform = self.viewport.board_transform(self.screen_shake.offset());
+        graphics::push_transform(ctx, Some(board_transform));
+        graphics::apply_transformations(ctx)?;
+
+        self.draw_board(ctx);
+        self.draw_playing(ctx);
+        if let GameState::GameOver = self.state {
+            self.draw_fatal_cell(ctx);
+        }
+        if self.debug_overlay {
+            self.draw_orientation_arrows(ctx);
+        }
+
+        graphics::pop_transform(ctx);
+        graphics::apply_transformations(ctx)?;
 
         match self.state {
+            GameState::Start => self.draw_start(ctx),
             GameState::GameOver => self.draw_game_over(ctx),
-            GameState::Playing => self.draw_playing(ctx),
-        };
+            GameState::Playing => self.draw_countdown(ctx),
+            GameState::Dying => (),
+        }
+        if self.show_stats {
+            let mut lines = self.profile.summary();
+            let (unlocked, total) = self.achievements.progress();
+            lines.push(format!("Achievements: {}/{}", unlocked, total));
+            self.draw_message(ctx, "Statistics", Some(lines.join("\n")));
+        }
+        self.draw_toast(ctx);
+        if self.debug_overlay {
+            self.draw_debug_overlay(ctx);
+        }
+        if self.console.is_open {
+            self.draw_console(ctx);
+        }
 
         graphics::present(ctx)
     }
-}
 
-enum GameState {
-    Playing,
-    GameOver,
-}
+    fn key_down_event(&mut self, ctx: &mut Context, keycode: KeyCode, _keymods: KeyMods, _repeat: bool) {
+        if self.console.is_open {
+            match keycode {
+                KeyCode::Escape | KeyCode::Grave => self.console.toggle(),
+                KeyCode::Back => self.console.pop(),
+                KeyCode::Return | KeyCode::NumpadEnter => {
+                    self.console.output = match self.console.submit() {
+                        Ok(command) => self.run_console_command(ctx, command),
+                        Err(e) => e,
+                    };
+                }
+                _ => (),
+            }
+            return;
+        }
+
+        match keycode {
+            KeyCode::Escape => {
+                self.autosave(ctx);
+                self.save_stats(ctx);
+                event::quit(ctx);
+            }
+            KeyCode::F11 => self.toggle_fullscreen(ctx),
+            KeyCode::F3 => self.debug_overlay = !self.debug_overlay,
+            KeyCode::Tab => self.show_stats = !self.show_stats,
+            KeyCode::Grave => self.console.toggle(),
+            KeyCode::P => self.paused = !self.paused,
+            KeyCode::Add | KeyCode::Equals => self.timestep.set_speed(self.timestep.speed() * 2.0),
+            KeyCode::Subtract | KeyCode::Minus => self.timestep.set_speed(self.timestep.speed() / 2.0),
+            _ => (),
+        }
+    }
+
+    fn quit_event(&mut self, ctx: &mut Context) -> bool {
+        self.autosave(ctx);
+        self.save_stats(ctx);
+        false
+    }
+
+    // The key opening the console also comes in as text, it is filtered out here.
+    fn text_input_event(&mut self, _ctx: &mut Context, character: char) {
+        if self.console.is_open && character != '`' {
+            self.console.push(character);
+        }
+    }
 
-impl GameState {
-    pub fn default() -> GameState {
-        GameState::Playing
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        self.viewport.resize(width, height);
+        graphics::set_screen_coordinates(ctx, self.viewport.screen_rect())
+            .expect("Failed to update the screen coordinates");
     }
 }
\ No newline at end of file
@@ -0,0 +1,240 @@
+use std::fmt;
+
+use serde::{Serialize, Deserialize};
+
+use crate::player::DeathCause;
+use crate::utils::constants::*;
+
+// What happens when the head runs into something.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Collision {
+    Die,
+    // The snake stays where it is for this tick.
+    Block,
+    // The bitten segment and everything behind it fall off.
+    CutTail,
+}
+
+// Rules of a game mode, consulted by `Game::tick`. The defaults are the classic rules.
+pub trait Rules {
+    fn name(&self) -> String;
+
+    // Ends the run before the tick is played, e.g. when the time is up.
+    fn check_end(&self, _ticks: u64) -> Option<DeathCause> {
+        None
+    }
+
+    fn collision(&self, _cause: &DeathCause) -> Collision {
+        Collision::Die
+    }
+
+    fn has_food(&self) -> bool {
+        true
+    }
+
+    // Whether the head can move into the cell the tail leaves on the same tick. The tail stays in
+    // place while the snake grows, so it is always a collision then.
+    fn tail_is_free(&self) -> bool {
+        true
+    }
+
+    // Segments added after the tick number `ticks` was played.
+    fn growth(&self, _ticks: u64) -> i32 {
+        0
+    }
+
+    // Seconds left before the run ends, for modes with a countdown. This is game time, as played at
+    // 1x, frontends divide it by the speed multiplier to show real seconds.
+    fn time_left(&self, _ticks: u64) -> Option<f32> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Classic;
+
+impl Rules for Classic {
+    fn name(&self) -> String {
+        String::from("Classic")
+    }
+}
+
+// Time is counted in ticks so replays and saves end at the same point, `seconds` is the length of
+// the run at 1x and shrinks at higher speeds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TimeAttack {
+    pub seconds: u32,
+}
+
+impl TimeAttack {
+    fn tick_count(&self) -> u64 {
+        (self.seconds as f32 / MOVE_DELAY).round() as u64
+    }
+}
+
+impl Rules for TimeAttack {
+    fn name(&self) -> String {
+        format!("Time attack ({}s)", self.seconds)
+    }
+
+    fn check_end(&self, ticks: u64) -> Option<DeathCause> {
+        if ticks >= self.tick_count() {
+            Some(DeathCause::TimedOut)
+        } else {
+            None
+        }
+    }
+
+    fn time_left(&self, ticks: u64) -> Option<f32> {
+        Some(self.tick_count().saturating_sub(ticks) as f32 * MOVE_DELAY)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Survival {
+    pub grow_every: u32,
+}
+
+impl Rules for Survival {
+    fn name(&self) -> String {
+        format!("Survival (grows every {} ticks)", self.grow_every)
+    }
+
+    fn has_food(&self) -> bool {
+        false
+    }
+
+    // Room is what the mode is about, the tail cell only counts once the tail is gone.
+    fn tail_is_free(&self) -> bool {
+        false
+    }
+
+    fn growth(&self, ticks: u64) -> i32 {
+        if ticks.is_multiple_of(self.grow_every as u64) {
+            1
+        } else {
+            0
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Zen;
+
+impl Rules for Zen {
+    fn name(&self) -> String {
+        String::from("Zen")
+    }
+
+    fn collision(&self, cause: &DeathCause) -> Collision {
+        match cause {
+            DeathCause::HitSelf(_, _) => Collision::CutTail,
+            _ => Collision::Block,
+        }
+    }
+}
+
+// Optional rules applied on top of any mode.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Variants {
+    // Biting the body cuts the tail off instead of ending the run, for a score penalty.
+    pub tail_cutting: bool,
+}
+
+impl Variants {
+    // Comma separated names as used in replays, `none` when no variant is enabled.
+    pub fn id(&self) -> String {
+        let mut names = Vec::new();
+        if self.tail_cutting {
+            names.push("tail-cutting");
+        }
+        if names.is_empty() {
+            String::from("none")
+        } else {
+            names.join(",")
+        }
+    }
+
+    pub fn by_id(id: &str) -> Option<Variants> {
+        let mut variants = Variants::default();
+        if id == "none" {
+            return Some(variants);
+        }
+        for name in id.split(',') {
+            match name {
+                "tail-cutting" => variants.tail_cutting = true,
+                _ => return None,
+            }
+        }
+        Some(variants)
+    }
+
+    pub fn collision(&self, collision: Collision, cause: &DeathCause) -> Collision {
+        match (collision, cause) {
+            (Collision::Die, DeathCause::HitSelf(_, _)) if self.tail_cutting => Collision::CutTail,
+            _ => collision,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum GameMode {
+    Classic(Classic),
+    TimeAttack(TimeAttack),
+    Survival(Survival),
+    Zen(Zen),
+}
+
+impl Default for GameMode {
+    fn default() -> GameMode {
+        GameMode::Classic(Classic)
+    }
+}
+
+impl GameMode {
+    pub fn rules(&self) -> &dyn Rules {
+        match self {
+            GameMode::Classic(rules) => rules,
+            GameMode::TimeAttack(rules) => rules,
+            GameMode::Survival(rules) => rules,
+            GameMode::Zen(rules) => rules,
+        }
+    }
+
+    // Names as used on the command line and in replays, e.g. `time-attack:120`.
+    pub fn id(&self) -> String {
+        match self {
+            GameMode::Classic(_) => String::from("classic"),
+            GameMode::TimeAttack(rules) => format!("time-attack:{}", rules.seconds),
+            GameMode::Survival(rules) => format!("survival:{}", rules.grow_every),
+            GameMode::Zen(_) => String::from("zen"),
+        }
+    }
+
+    pub fn by_id(id: &str) -> Option<GameMode> {
+        let mut parts = id.splitn(2, ':');
+        let name = parts.next()?;
+        let parameter = match parts.next() {
+            Some(parameter) => Some(parameter.parse::<u32>().ok().filter(|&value| value > 0)?),
+            None => None,
+        };
+
+        match name {
+            "classic" if parameter.is_none() => Some(GameMode::Classic(Classic)),
+            "time-attack" => Some(GameMode::TimeAttack(TimeAttack {
+                seconds: parameter.unwrap_or(TIME_ATTACK_SECONDS),
+            })),
+            "survival" => Some(GameMode::Survival(Survival {
+                grow_every: parameter.unwrap_or(SURVIVAL_GROW_EVERY),
+            })),
+            "zen" if parameter.is_none() => Some(GameMode::Zen(Zen)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for GameMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.rules().name())
+    }
+}
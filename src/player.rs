@@ -1,112 +1,232 @@
-use std::collections::HashSet;
-use crate::utils::{Vec2D, Rectangle, Direction};
+use std::collections::VecDeque;
+use std::fmt;
+use serde::{Serialize, Deserialize};
+use crate::board::{Board, Cell, SnakeId};
+use crate::spawn::Spawn;
+use crate::utils::{Vec2D, Direction};
+use crate::utils::constants::MIN_LENGTH;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Player {
+    pub id: SnakeId,
     pub direction: Direction,
     pub wanted_direction: Option<Direction>,
-    pub body: Vec<SnakeBodyPart>,
-    pub body_positions: HashSet<Vec2D>,
+    pub body: VecDeque<SnakeBodyPart>,
+    pub pending_growth: i32,
+    head_sequence: u32,
 }
 
 impl Player {
-    pub fn new() -> Player {
+    pub fn new(id: SnakeId) -> Player {
         Player {
-            direction: Direction::Up,
+            id,
+            direction: Direction::Right,
             wanted_direction: None,
-            body: Player::default_position(),
-            body_positions: HashSet::with_capacity(5)
+            body: VecDeque::new(),
+            pending_growth: 0,
+            head_sequence: 0,
         }
     }
 
-    pub fn reset(&mut self) {
-        self.direction = Direction::Right;
-        self.wanted_direction = None;
-        self.body = Player::default_position();
-        self.recompute_body_positions();
-    }
-
-    pub fn can_move(&self, playing_area: &Rectangle) -> bool {
-        let dest = match self.direction {
-            Direction::Up => &self.body[0].position + Vec2D::new(0, -1),
-            Direction::Down => &self.body[0].position + Vec2D::new(0, 1),
-            Direction::Right => &self.body[0].position + Vec2D::new(1, 0),
-            Direction::Left => &self.body[0].position + Vec2D::new(-1, 0),
-        };
-
-        playing_area.contains(&dest) && !self.body_positions.contains(&dest)
-    }
-
-    pub fn r#move(&mut self) {
-        let dest = &self.body[0].position + match self.direction {
-            Direction::Up => Vec2D::new(0, -1),
-            Direction::Down => Vec2D::new(0, 1),
-            Direction::Right => Vec2D::new(1, 0),
-            Direction::Left => Vec2D::new(-1, 0),
-        };
-
-        for i in (0..self.body.len()).rev() {
-            if i == (self.body.len() - 1) {
-                let tail_position = self.body[i].position;
-                if self.body[i].is_big {
-                    let mut new_tail = SnakeBodyPart::new_tail(tail_position.x, tail_position.y);
-                    new_tail.orientation = self.body[i].orientation;
-                    self.body.push(new_tail);
-
-                    self.body[i].is_tail = false;
-                    self.body[i].orientation = self.body[i - 1].orientation;
-                } else {
-                    self.body_positions.remove(&tail_position);
-
-                    self.body[i].orientation = (self.body[i - 1].orientation.0, None);
+    pub fn reset(&mut self, board: &mut Board, spawn: &Spawn) {
+        for body_part in self.body.iter() {
+            if let Some(Cell::Snake(id, _)) = board.get(&body_part.position) {
+                if id == self.id {
+                    board.set(&body_part.position, Cell::Empty);
                 }
-                self.body[i].position = self.body[i - 1].position;
-                self.body[i].is_big = self.body[i - 1].is_big;
-            } else if i == 0 {
-                self.body[i].position = dest;
-                self.body[i].is_big = false;
-                self.body[i].orientation = (None, Some(self.direction));
+            }
+        }
+
+        self.direction = spawn.direction;
+        self.wanted_direction = None;
+        self.body = Player::spawn_body(spawn);
+        self.pending_growth = 0;
+        self.head_sequence = self.body.len() as u32 - 1;
+        for (i, body_part) in self.body.iter().enumerate() {
+            board.set(&body_part.position, Cell::Snake(self.id, self.head_sequence - i as u32));
+        }
+    }
+
+    // Index in `body` of the segment with the given sequence number, as stored in `Cell::Snake`.
+    pub fn segment_index(&self, sequence: u32) -> usize {
+        self.head_sequence.wrapping_sub(sequence) as usize
+    }
+
+    // Returns the cell the head would move to, or why moving there kills the snake.
+    // With `free_tail`, the tail cell counts as free when the tail leaves it during this move.
+    pub fn check_move(&self, board: &Board, free_tail: bool) -> Result<Vec2D, DeathCause> {
+        let dest = board.step(&self.body[0].position, self.direction);
+        let tail_moves = free_tail && self.pending_growth <= 0;
+
+        match board.get(&dest) {
+            // Coming out of a portal right into another one blocks like a wall.
+            None | Some(Cell::Wall) | Some(Cell::Portal) => Err(DeathCause::HitWall(dest)),
+            Some(Cell::Snake(id, sequence)) if id == self.id && tail_moves && self.segment_index(sequence) == self.body.len() - 1 => {
+                Ok(dest)
+            }
+            Some(Cell::Snake(id, sequence)) if id == self.id => Err(DeathCause::HitSelf(dest, self.segment_index(sequence))),
+            Some(Cell::Snake(id, _)) => Err(DeathCause::HitSnake(dest, id)),
+            Some(Cell::Hazard) => Err(DeathCause::HitHazard(dest)),
+            Some(Cell::Empty) | Some(Cell::Food(_)) => Ok(dest),
+        }
+    }
+
+    pub fn r#move(&mut self, board: &mut Board) {
+        let dest = board.step(&self.body[0].position, self.direction);
 
-                self.body[i + 1].orientation.0 = Some(self.direction);
+        // Segments never change once laid down, only the old head and the new tail need updating.
+        self.body[0].is_head = false;
+        self.body[0].orientation.0 = Some(self.direction);
+        self.body.push_front(SnakeBodyPart::new_head_with_orientation(dest.x, dest.y, Some(self.direction)));
+        self.head_sequence = self.head_sequence.wrapping_add(1);
+        board.set(&dest, Cell::Snake(self.id, self.head_sequence));
 
-                self.body_positions.insert(dest);
+        if self.pending_growth > 0 {
+            self.pending_growth -= 1;
+        } else {
+            self.pop_tail(board);
+        }
+
+        if self.pending_growth < 0 {
+            if self.body.len() > MIN_LENGTH {
+                self.pop_tail(board);
+                self.pending_growth += 1;
             } else {
-                self.body[i].position = self.body[i - 1].position;
-                self.body[i].orientation = self.body[i - 1].orientation;
-                self.body[i].is_big = self.body[i - 1].is_big;
+                self.pending_growth = 0;
             }
         }
     }
 
-    fn recompute_body_positions(&mut self) {
-        self.body_positions.clear();
-        self.body_positions.extend(self.body.iter().map(|body_part| { body_part.position }));
+    // A positive amount adds segments at the tail, a negative one removes them, one per move.
+    pub fn grow(&mut self, amount: i32) {
+        self.pending_growth += amount;
+    }
+
+    // Drops the segment at `index` and everything behind it, the segment before becomes the tail.
+    // Returns how many segments were removed.
+    pub fn cut_at(&mut self, index: usize, board: &mut Board) -> usize {
+        debug_assert!(index >= MIN_LENGTH);
+        let removed = self.body.len() - index;
+        for body_part in self.body.drain(index..) {
+            board.set(&body_part.position, Cell::Empty);
+        }
+        self.mark_tail();
+        removed
+    }
+
+    fn pop_tail(&mut self, board: &mut Board) {
+        let tail = self.body.pop_back().unwrap();
+        if tail.position != self.body[0].position {
+            board.set(&tail.position, Cell::Empty);
+        }
+        self.mark_tail();
+    }
+
+    fn mark_tail(&mut self) {
+        let new_tail = self.body.back_mut().unwrap();
+        new_tail.is_tail = true;
+        new_tail.is_big = false;
+        new_tail.orientation.1 = None;
     }
 
     pub fn eat(&mut self) {
         self.body[0].is_big = true;
+        self.grow(1);
     }
 
-    fn default_position() -> Vec<SnakeBodyPart> {
-        vec![
-            SnakeBodyPart::new_head_with_orientation(6, 5, Some(Direction::Right)),
-            SnakeBodyPart::new_with_orientation(5, 5, Some(Direction::Right), Some(Direction::Right)),
-            SnakeBodyPart::new_with_orientation(4, 5, Some(Direction::Right), Some(Direction::Right)),
-            SnakeBodyPart::new_with_orientation(3, 5, Some(Direction::Right), Some(Direction::Right)),
-            SnakeBodyPart::new_with_orientation(2, 5, Some(Direction::Right), Some(Direction::Right)),
-            SnakeBodyPart::new_with_orientation(1, 5, Some(Direction::Right), Some(Direction::Right)),
-            SnakeBodyPart::new_tail_with_orientation(0, 5, Some(Direction::Right)),
-        ]
+    // Checks that the body forms a connected chain from the head to the tail. Consecutive segments
+    // are either adjacent or on each side of a portal of `board`.
+    pub fn validate_body(&self, board: &Board) -> Result<(), BodyError> {
+        let length = self.body.len();
+        if length < MIN_LENGTH {
+            return Err(BodyError::TooShort(length));
+        }
+
+        for (i, body_part) in self.body.iter().enumerate() {
+            if body_part.is_head != (i == 0) {
+                return Err(BodyError::MisplacedHead(i));
+            }
+            if body_part.is_tail != (i == length - 1) {
+                return Err(BodyError::MisplacedTail(i));
+            }
+            body_part.get_sprite_and_rotation().map_err(|error| BodyError::Segment(i, error))?;
+
+            if i > 0 {
+                let previous = &self.body[i - 1];
+                let toward_head = body_part.orientation.0.unwrap();
+                if Some(toward_head) != previous.orientation.1 {
+                    return Err(BodyError::MismatchedDirections(i));
+                }
+                if board.step(&body_part.position, toward_head) != previous.position {
+                    return Err(BodyError::NotAdjacent(i));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn spawn_body(spawn: &Spawn) -> VecDeque<SnakeBodyPart> {
+        let direction = Some(spawn.direction);
+        let last = spawn.length - 1;
+        spawn.cells().enumerate().map(|(i, cell)| match i {
+            0 => SnakeBodyPart::new_head_with_orientation(cell.x, cell.y, direction),
+            i if i == last => SnakeBodyPart::new_tail_with_orientation(cell.x, cell.y, direction),
+            _ => SnakeBodyPart::new_with_orientation(cell.x, cell.y, direction, direction),
+        }).collect()
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeathCause {
+    HitWall(Vec2D),
+    HitSelf(Vec2D, usize),
+    HitSnake(Vec2D, SnakeId),
+    HitHazard(Vec2D),
+    TimedOut,
+}
+
+impl DeathCause {
+    // Short name of the kind of death, the position and details left out.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeathCause::HitWall(_) => "Wall",
+            DeathCause::HitSelf(_, _) => "Own body",
+            DeathCause::HitSnake(_, _) => "Other snake",
+            DeathCause::HitHazard(_) => "Hazard",
+            DeathCause::TimedOut => "Out of time",
+        }
+    }
+
+    pub fn position(&self) -> Option<Vec2D> {
+        match *self {
+            DeathCause::HitWall(position)
+            | DeathCause::HitSelf(position, _)
+            | DeathCause::HitSnake(position, _)
+            | DeathCause::HitHazard(position) => Some(position),
+            DeathCause::TimedOut => None,
+        }
+    }
+}
+
+impl fmt::Display for DeathCause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeathCause::HitWall(position) => write!(f, "Hit a wall at ({}, {})", position.x, position.y),
+            DeathCause::HitSelf(_, segment) => write!(f, "Bit its own body at segment {}", segment),
+            DeathCause::HitSnake(_, id) => write!(f, "Ran into snake {}", id),
+            DeathCause::HitHazard(position) => write!(f, "Ran into a hazard at ({}, {})", position.x, position.y),
+            DeathCause::TimedOut => write!(f, "Ran out of time"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SnakeBodyPart {
     pub is_head: bool,
     pub is_tail: bool,
     pub is_big: bool,
     pub position: Vec2D,
-    pub orientation: (Option<Direction>, Option<Direction>)
+    pub orientation: Orientation
 }
 
 impl SnakeBodyPart {
@@ -139,25 +259,25 @@ impl SnakeBodyPart {
     }
 
     pub fn new_head_with_orientation(x: i32, y: i32, orientation: Option<Direction>) -> SnakeBodyPart {
-        let mut body_part = SnakeBodyPart::new_with_orientation(x, y, None, orientation);
-        body_part.is_head = true;
+        let mut body_part = SnakeBodyPart::new_head(x, y);
+        body_part.orientation = (None, orientation);
         body_part
     }
 
     pub fn new_tail_with_orientation(x: i32, y: i32, orientation: Option<Direction>) -> SnakeBodyPart {
-        let mut body_part = SnakeBodyPart::new_with_orientation(x, y, orientation, None);
-        body_part.is_tail = true;
+        let mut body_part = SnakeBodyPart::new_tail(x, y);
+        body_part.orientation = (orientation, None);
         body_part
     }
 
-    pub fn get_sprite_and_rotation(&self) -> Result<(SpriteType, f32), &'static str> {
+    pub fn get_sprite_and_rotation(&self) -> Result<(SpriteType, f32), SegmentError> {
         if self.is_tail {
             match self.orientation {
                 (Some(Direction::Up), None) => Ok((SpriteType::Tail, 0.0)),
                 (Some(Direction::Right), None) => Ok((SpriteType::Tail, std::f32::consts::FRAC_PI_2)),
                 (Some(Direction::Down), None) => Ok((SpriteType::Tail, std::f32::consts::PI)),
                 (Some(Direction::Left), None) => Ok((SpriteType::Tail, -std::f32::consts::FRAC_PI_2)),
-                _ => Err("Error")
+                orientation => Err(SegmentError::InvalidTail(orientation))
             }
         } else if self.is_head {
             match self.orientation {
@@ -165,7 +285,7 @@ impl SnakeBodyPart {
                 (None, Some(Direction::Right)) => Ok((SpriteType::Head, std::f32::consts::FRAC_PI_2)),
                 (None, Some(Direction::Down)) => Ok((SpriteType::Head, std::f32::consts::PI)),
                 (None, Some(Direction::Left)) => Ok((SpriteType::Head, -std::f32::consts::FRAC_PI_2)),
-                _ => Err("Error")
+                orientation => Err(SegmentError::InvalidHead(orientation))
             }
         } else {
             match self.orientation {
@@ -181,15 +301,54 @@ impl SnakeBodyPart {
                 (Some(Direction::Down), Some(Direction::Right)) => Ok((SpriteType::Right, std::f32::consts::PI)),
                 (Some(Direction::Left), Some(Direction::Up)) => Ok((SpriteType::Left, -std::f32::consts::FRAC_PI_2)),
                 (Some(Direction::Left), Some(Direction::Down)) => Ok((SpriteType::Right, -std::f32::consts::FRAC_PI_2)),
-                _ => Err("Error")
+                orientation => Err(SegmentError::InvalidBody(orientation))
             }
         }
     }
 }
 
-impl From<(i32, i32)> for Vec2D {
-    fn from(p: (i32, i32)) -> Self {
-        p.into()
+// Direction toward the head, then direction the snake moved into the segment. Stored rather than
+// derived from the neighbouring cells, which are not adjacent across a portal.
+pub type Orientation = (Option<Direction>, Option<Direction>);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SegmentError {
+    InvalidHead(Orientation),
+    InvalidTail(Orientation),
+    InvalidBody(Orientation),
+}
+
+impl fmt::Display for SegmentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SegmentError::InvalidHead(orientation) => write!(f, "Invalid head orientation {:?}", orientation),
+            SegmentError::InvalidTail(orientation) => write!(f, "Invalid tail orientation {:?}", orientation),
+            SegmentError::InvalidBody(orientation) => write!(f, "Invalid body orientation {:?}", orientation),
+        }
+    }
+}
+
+// Inconsistencies in `Player::body`, indices are positions in `body`, starting at the head.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BodyError {
+    TooShort(usize),
+    Segment(usize, SegmentError),
+    MisplacedHead(usize),
+    MisplacedTail(usize),
+    NotAdjacent(usize),
+    MismatchedDirections(usize),
+}
+
+impl fmt::Display for BodyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BodyError::TooShort(length) => write!(f, "Body has only {} segments", length),
+            BodyError::Segment(index, error) => write!(f, "Segment {}: {}", index, error),
+            BodyError::MisplacedHead(index) => write!(f, "Segment {} is marked as the head", index),
+            BodyError::MisplacedTail(index) => write!(f, "Segment {} is marked as the tail", index),
+            BodyError::NotAdjacent(index) => write!(f, "Segment {} is not next to the one before it", index),
+            BodyError::MismatchedDirections(index) => write!(f, "Segment {} does not point to the one before it", index),
+        }
     }
 }
 
@@ -199,4 +358,233 @@ pub enum SpriteType {
     Straight,
     Left,
     Right,
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::Rectangle;
+
+    // A snake of `length` heading right from (10, 10), with room ahead for several moves.
+    fn spawn(length: usize) -> (Player, Board) {
+        let mut board = Board::new(&Rectangle::new(0, 0, 19, 19));
+        let mut player = Player::new(0);
+        player.reset(&mut board, &Spawn::new(Vec2D::new(10, 10), Direction::Right, length));
+        (player, board)
+    }
+
+    fn move_times(player: &mut Player, board: &mut Board, count: usize) {
+        for _ in 0..count {
+            player.r#move(board);
+            assert_eq!(player.validate_body(board), Ok(()));
+        }
+    }
+
+    fn snake_cells(board: &Board) -> usize {
+        board.area().cells().filter(|cell| matches!(board.get(cell), Some(Cell::Snake(_, _)))).count()
+    }
+
+    #[test]
+    fn move_without_growth_keeps_length() {
+        let (mut player, mut board) = spawn(4);
+        move_times(&mut player, &mut board, 3);
+        assert_eq!(player.body.len(), 4);
+        assert_eq!(player.body[0].position, Vec2D::new(13, 10));
+        assert_eq!(player.body[3].position, Vec2D::new(10, 10));
+        assert_eq!(board.get(&Vec2D::new(9, 10)), Some(Cell::Empty));
+        assert_eq!(snake_cells(&board), 4);
+    }
+
+    #[test]
+    fn positive_growth_adds_one_segment_per_move() {
+        let (mut player, mut board) = spawn(4);
+        player.grow(3);
+        for expected in 5..=7 {
+            move_times(&mut player, &mut board, 1);
+            assert_eq!(player.body.len(), expected);
+        }
+        assert_eq!(player.pending_growth, 0);
+        move_times(&mut player, &mut board, 2);
+        assert_eq!(player.body.len(), 7);
+        assert_eq!(snake_cells(&board), 7);
+    }
+
+    #[test]
+    fn negative_growth_removes_one_segment_per_move() {
+        let (mut player, mut board) = spawn(6);
+        player.grow(-2);
+        move_times(&mut player, &mut board, 1);
+        assert_eq!(player.body.len(), 5);
+        assert_eq!(player.pending_growth, -1);
+        move_times(&mut player, &mut board, 1);
+        assert_eq!(player.body.len(), 4);
+        assert_eq!(player.pending_growth, 0);
+        move_times(&mut player, &mut board, 1);
+        assert_eq!(player.body.len(), 4);
+        assert_eq!(snake_cells(&board), 4);
+    }
+
+    #[test]
+    fn shrinking_stops_at_min_length() {
+        let (mut player, mut board) = spawn(MIN_LENGTH + 1);
+        player.grow(-5);
+        move_times(&mut player, &mut board, 3);
+        assert_eq!(player.body.len(), MIN_LENGTH);
+        assert_eq!(player.pending_growth, 0);
+        assert_eq!(snake_cells(&board), MIN_LENGTH);
+    }
+
+    #[test]
+    fn growth_and_shrink_cancel_out() {
+        let (mut player, mut board) = spawn(4);
+        player.grow(2);
+        player.grow(-2);
+        move_times(&mut player, &mut board, 2);
+        assert_eq!(player.body.len(), 4);
+    }
+
+    #[test]
+    fn lump_travels_down_the_body() {
+        let (mut player, mut board) = spawn(4);
+        player.eat();
+        let lump = player.body[0].position;
+        assert!(player.body[0].is_big);
+
+        for i in 1..=3 {
+            move_times(&mut player, &mut board, 1);
+            let big: Vec<usize> = (0..player.body.len()).filter(|&j| player.body[j].is_big).collect();
+            assert_eq!(big, vec![i]);
+            assert_eq!(player.body[i].position, lump);
+        }
+        assert_eq!(player.body.len(), 5);
+
+        // The lump is gone once its segment becomes the tail.
+        move_times(&mut player, &mut board, 1);
+        assert!(player.body.iter().all(|body_part| !body_part.is_big));
+        assert_eq!(player.body.len(), 5);
+    }
+
+    #[test]
+    fn spawned_body_is_valid() {
+        let (player, board) = spawn(5);
+        assert_eq!(player.validate_body(&board), Ok(()));
+    }
+
+    #[test]
+    fn validate_body_reports_short_bodies() {
+        let (mut player, board) = spawn(4);
+        player.body.truncate(1);
+        assert_eq!(player.validate_body(&board), Err(BodyError::TooShort(1)));
+    }
+
+    #[test]
+    fn validate_body_reports_broken_segments() {
+        let (mut player, board) = spawn(4);
+        player.body[1].orientation = (None, None);
+        assert_eq!(
+            player.validate_body(&board),
+            Err(BodyError::Segment(1, SegmentError::InvalidBody((None, None))))
+        );
+
+        let (mut player, board) = spawn(4);
+        player.body[0].orientation = (Some(Direction::Right), None);
+        assert_eq!(
+            player.validate_body(&board),
+            Err(BodyError::Segment(0, SegmentError::InvalidHead((Some(Direction::Right), None))))
+        );
+
+        let (mut player, board) = spawn(4);
+        player.body[3].orientation = (None, Some(Direction::Right));
+        assert_eq!(
+            player.validate_body(&board),
+            Err(BodyError::Segment(3, SegmentError::InvalidTail((None, Some(Direction::Right)))))
+        );
+    }
+
+    #[test]
+    fn validate_body_reports_misplaced_ends() {
+        let (mut player, board) = spawn(4);
+        player.body[2].is_head = true;
+        assert_eq!(player.validate_body(&board), Err(BodyError::MisplacedHead(2)));
+
+        let (mut player, board) = spawn(4);
+        player.body[1].is_tail = true;
+        assert_eq!(player.validate_body(&board), Err(BodyError::MisplacedTail(1)));
+    }
+
+    #[test]
+    fn validate_body_reports_gaps() {
+        let (mut player, board) = spawn(4);
+        player.body[2].position = Vec2D::new(0, 0);
+        assert_eq!(player.validate_body(&board), Err(BodyError::NotAdjacent(2)));
+    }
+
+    #[test]
+    fn validate_body_reports_mismatched_directions() {
+        let (mut player, board) = spawn(4);
+        player.body[2].orientation = (Some(Direction::Up), Some(Direction::Up));
+        assert_eq!(player.validate_body(&board), Err(BodyError::MismatchedDirections(2)));
+    }
+
+    // A snake of 4 curled into a square, with its head right below its tail and heading up.
+    fn head_behind_tail() -> (Player, Board) {
+        let (mut player, mut board) = spawn(4);
+        for direction in [Direction::Down, Direction::Left].iter() {
+            player.direction = *direction;
+            player.r#move(&mut board);
+        }
+        player.direction = Direction::Up;
+        assert_eq!(player.body[3].position, Vec2D::new(9, 10));
+        (player, board)
+    }
+
+    #[test]
+    fn head_follows_the_tail_when_not_growing() {
+        let (mut player, mut board) = head_behind_tail();
+        assert_eq!(player.check_move(&board, true), Ok(Vec2D::new(9, 10)));
+
+        player.r#move(&mut board);
+        assert_eq!(player.validate_body(&board), Ok(()));
+        assert_eq!(player.body.len(), 4);
+        assert_eq!(player.body[0].position, Vec2D::new(9, 10));
+        assert_eq!(board.get(&Vec2D::new(9, 10)), Some(Cell::Snake(player.id, player.head_sequence)));
+        assert_eq!(board.get(&Vec2D::new(10, 10)), Some(Cell::Snake(player.id, player.head_sequence - 3)));
+    }
+
+    #[test]
+    fn head_hits_the_tail_when_growing() {
+        let (mut player, board) = head_behind_tail();
+        player.grow(1);
+        assert_eq!(player.check_move(&board, true), Err(DeathCause::HitSelf(Vec2D::new(9, 10), 3)));
+    }
+
+    #[test]
+    fn head_hits_the_tail_when_it_is_not_free() {
+        let (player, board) = head_behind_tail();
+        assert_eq!(player.check_move(&board, false), Err(DeathCause::HitSelf(Vec2D::new(9, 10), 3)));
+    }
+
+    #[test]
+    fn cutting_at_a_turn_leaves_a_valid_tail() {
+        let (mut player, mut board) = spawn(8);
+        for direction in [Direction::Down, Direction::Left].iter() {
+            player.direction = *direction;
+            player.r#move(&mut board);
+        }
+        player.eat();
+        player.body[2].is_big = true;
+
+        assert_eq!(player.cut_at(2, &mut board), 6);
+        assert_eq!(player.body.len(), 2);
+        assert_eq!(snake_cells(&board), 2);
+        let tail = player.body.back().unwrap();
+        assert_eq!(tail.position, Vec2D::new(10, 11));
+        assert!(tail.is_tail && !tail.is_big);
+        assert_eq!(tail.orientation, (Some(Direction::Left), None));
+        assert!(matches!(tail.get_sprite_and_rotation(), Ok((SpriteType::Tail, _))));
+        assert_eq!(player.validate_body(&board), Ok(()));
+
+        // Growth still pending from the food is not lost.
+        move_times(&mut player, &mut board, 1);
+        assert_eq!(player.body.len(), 3);
+    }
+}
@@ -0,0 +1,255 @@
+use std::io::{self, Read, Write};
+
+use serde::{Serialize, Deserialize};
+
+use crate::food::FoodConf;
+use crate::game::{Game, TickOutcome};
+use crate::hazard::HazardConf;
+use crate::level;
+use crate::mode::{GameMode, Variants};
+use crate::spawn::{SpawnConf, Placement};
+use crate::utils::{Vec2D, Rectangle, Direction};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub width: u32,
+    pub height: u32,
+    pub spawn: SpawnConf,
+    pub food: FoodConf,
+    pub hazards: HazardConf,
+    pub portals: Vec<(Vec2D, Vec2D)>,
+    pub mode: GameMode,
+    pub variants: Variants,
+    pub inputs: Vec<Option<Direction>>,
+}
+
+impl Replay {
+    // Starts recording a game that was just reset.
+    pub fn new(game: &Game) -> Replay {
+        Replay {
+            seed: game.seed(),
+            width: game.playing_area.width(),
+            height: game.playing_area.height(),
+            spawn: game.spawn.clone(),
+            food: game.food_conf.clone(),
+            hazards: game.hazard_conf.clone(),
+            portals: game.board.portals().to_vec(),
+            mode: game.mode,
+            variants: game.variants,
+            inputs: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, input: Option<Direction>) {
+        self.inputs.push(input);
+    }
+
+    // Runs the recorded inputs against a fresh game, calling `on_frame` with the initial state and after every tick.
+    pub fn play<F: FnMut(&Game)>(&self, mut on_frame: F) -> io::Result<()> {
+        let playing_area = Rectangle::new(0, 0, self.width as i32 - 1, self.height as i32 - 1);
+        let mut game = Game::new(
+            playing_area,
+            self.spawn.clone(),
+            self.food.clone(),
+            self.hazards.clone(),
+            self.portals.clone(),
+            self.mode,
+            self.seed
+        ).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        game.variants = self.variants;
+        on_frame(&game);
+
+        for input in self.inputs.iter() {
+            game.player.wanted_direction = *input;
+            let outcome = game.tick();
+            on_frame(&game);
+            if let TickOutcome::Died(_) = outcome {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let inputs: String = self.inputs.iter().map(|input| match input {
+            Some(Direction::Up) => 'U',
+            Some(Direction::Down) => 'D',
+            Some(Direction::Left) => 'L',
+            Some(Direction::Right) => 'R',
+            None => '-',
+        }).collect();
+        writeln!(
+            writer,
+            "{} {} {} {} {} {} {} {} {} {}",
+            self.seed,
+            self.width,
+            self.height,
+            self.spawn.length,
+            self.spawn.placement.name(),
+            self.mode.id(),
+            self.variants.id(),
+            self.food.id(),
+            self.hazards.id(),
+            level::portals_id(&self.portals)
+        )?;
+        writeln!(writer, "{}", inputs)
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Replay> {
+        let mut content = String::new();
+        reader.read_to_string(&mut content)?;
+
+        let mut lines = content.lines();
+        let header: Vec<&str> = lines.next().unwrap_or("").split_whitespace().collect();
+        // Older replays stop early, after the size, the spawn settings or any later field.
+        if ![3, 5, 6, 7, 8, 9, 10].contains(&header.len()) {
+            return Err(invalid_data("Invalid replay header"));
+        }
+        let seed = header[0].parse().map_err(|_| invalid_data("Invalid replay seed"))?;
+        let width = header[1].parse().map_err(|_| invalid_data("Invalid replay width"))?;
+        let height = header[2].parse().map_err(|_| invalid_data("Invalid replay height"))?;
+        let mut spawn = SpawnConf::default();
+        if header.len() >= 5 {
+            spawn.length = header[3].parse().map_err(|_| invalid_data("Invalid replay snake length"))?;
+            spawn.placement = Placement::by_name(header[4]).ok_or_else(|| invalid_data("Invalid replay spawn placement"))?;
+        }
+        let mut mode = GameMode::default();
+        if header.len() >= 6 {
+            mode = GameMode::by_id(header[5]).ok_or_else(|| invalid_data("Invalid replay game mode"))?;
+        }
+        let mut variants = Variants::default();
+        if header.len() >= 7 {
+            variants = Variants::by_id(header[6]).ok_or_else(|| invalid_data("Invalid replay rule variants"))?;
+        }
+        let mut food = FoodConf::default();
+        if header.len() >= 8 {
+            food = FoodConf::by_id(header[7]).ok_or_else(|| invalid_data("Invalid replay food settings"))?;
+        }
+        let mut hazards = HazardConf::default();
+        if header.len() >= 9 {
+            hazards = HazardConf::by_id(header[8]).ok_or_else(|| invalid_data("Invalid replay hazard settings"))?;
+        }
+        let mut portals = Vec::new();
+        if header.len() == 10 {
+            portals = level::portals_by_id(header[9]).ok_or_else(|| invalid_data("Invalid replay portals"))?;
+        }
+
+        let mut inputs = Vec::new();
+        for c in lines.next().unwrap_or("").chars() {
+            inputs.push(match c {
+                'U' => Some(Direction::Up),
+                'D' => Some(Direction::Down),
+                'L' => Some(Direction::Left),
+                'R' => Some(Direction::Right),
+                '-' => None,
+                _ => return Err(invalid_data("Invalid replay input")),
+            });
+        }
+
+        Ok(Replay {
+            seed,
+            width,
+            height,
+            spawn,
+            food,
+            hazards,
+            portals,
+            mode,
+            variants,
+            inputs,
+        })
+    }
+}
+
+fn invalid_data(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mode::{Survival, Zen};
+
+    fn game() -> Game {
+        let area = Rectangle::new(0, 0, 14, 11);
+        let spawn = SpawnConf { length: 4, placement: Placement::Random };
+        let food = FoodConf { count: 2, ..FoodConf::default() };
+        let hazards = HazardConf { bouncers: 1, ..HazardConf::default() };
+        let portals = vec![(Vec2D::new(1, 1), Vec2D::new(13, 10))];
+        let mut game = Game::new(area, spawn, food, hazards, portals, GameMode::Survival(Survival { grow_every: 3 }), 42)
+            .unwrap();
+        game.variants = Variants { tail_cutting: true };
+        game
+    }
+
+    fn written(replay: &Replay) -> String {
+        let mut content = Vec::new();
+        replay.write(&mut content).unwrap();
+        String::from_utf8(content).unwrap()
+    }
+
+    fn header(replay: &Replay) -> Vec<String> {
+        written(replay).lines().next().unwrap().split_whitespace().map(String::from).collect()
+    }
+
+    fn final_state(replay: &Replay) -> String {
+        let mut last = String::new();
+        replay.play(|game| last = serde_json::to_string(game).unwrap()).unwrap();
+        last
+    }
+
+    #[test]
+    fn written_replays_read_back_the_same() {
+        let mut replay = Replay::new(&game());
+        for input in [None, Some(Direction::Up), Some(Direction::Left), None, Some(Direction::Down), Some(Direction::Right)].iter() {
+            replay.record(*input);
+        }
+        let content = written(&replay);
+        assert_eq!(header(&replay).len(), 10);
+        assert!(content.ends_with("\n-UL-DR\n"));
+
+        let read = Replay::read(content.as_bytes()).unwrap();
+        assert_eq!(written(&read), content);
+        assert_eq!(final_state(&read), final_state(&replay));
+    }
+
+    #[test]
+    fn older_headers_fall_back_to_the_defaults() {
+        let mut replay = Replay::new(&game());
+        replay.mode = GameMode::Zen(Zen);
+        let full = header(&replay);
+        let defaults = header(&Replay::read("42 15 12\n".as_bytes()).unwrap());
+        assert_eq!(defaults[3..], [
+            SpawnConf::default().length.to_string(),
+            SpawnConf::default().placement.name().to_string(),
+            GameMode::default().id(),
+            Variants::default().id(),
+            FoodConf::default().id(),
+            HazardConf::default().id(),
+            level::portals_id(&[]),
+        ]);
+
+        for &length in [3, 5, 6, 7, 8, 9].iter() {
+            let content = format!("{}\nUD\n", full[..length].join(" "));
+            let read = Replay::read(content.as_bytes()).unwrap();
+            let expected: Vec<String> = full[..length].iter().chain(defaults[length..].iter()).cloned().collect();
+            assert_eq!(header(&read), expected, "{} fields", length);
+            assert_eq!(read.inputs, vec![Some(Direction::Up), Some(Direction::Down)]);
+        }
+    }
+
+    #[test]
+    fn rejects_broken_replays() {
+        for content in ["", "42 15", "42 15 12 4", "x 15 12", "42 15 12\nUX"].iter() {
+            assert_eq!(Replay::read(content.as_bytes()).err().map(|e| e.kind()), Some(io::ErrorKind::InvalidData), "{:?}", content);
+        }
+    }
+
+    #[test]
+    fn replays_without_room_for_the_snake_are_invalid() {
+        let mut replay = Replay::read("1 10 10\nRR\n".as_bytes()).unwrap();
+        replay.portals = vec![(Vec2D::new(5, 5), Vec2D::new(0, 0))];
+        assert_eq!(replay.play(|_| ()).err().map(|e| e.kind()), Some(io::ErrorKind::InvalidData));
+    }
+}
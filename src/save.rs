@@ -0,0 +1,94 @@
+use std::io::{self, Read, Write};
+
+use serde::{Serialize, Deserialize};
+
+use crate::game::Game;
+use crate::replay::Replay;
+
+// Everything needed to pick a run up where it was left. The replay is missing when the console
+// changed the game, since it could not reproduce it anymore.
+#[derive(Serialize, Deserialize)]
+pub struct SaveState {
+    pub game: Game,
+    pub replay: Option<Replay>,
+    pub timer_accumulator: f32,
+}
+
+impl SaveState {
+    pub fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+        serde_json::to_writer(writer, self)?;
+        Ok(())
+    }
+
+    pub fn read<R: Read>(reader: R) -> io::Result<SaveState> {
+        let save: SaveState = serde_json::from_reader(reader)?;
+        save.game.player.validate_body(&save.game.board)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        Ok(save)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::food::FoodConf;
+    use crate::hazard::HazardConf;
+    use crate::mode::{GameMode, Zen};
+    use crate::spawn::SpawnConf;
+    use crate::utils::{Rectangle, Direction};
+
+    // Heads for the first food item, so that the snake eats and new food is drawn from the RNG.
+    fn steer(game: &mut Game) {
+        let head = game.player.body[0].position;
+        let target = game.food.first().map_or(head, |food| food.position);
+        game.player.wanted_direction = Some(if target.x > head.x {
+            Direction::Right
+        } else if target.x < head.x {
+            Direction::Left
+        } else if target.y > head.y {
+            Direction::Down
+        } else {
+            Direction::Up
+        });
+    }
+
+    #[test]
+    fn resumed_games_keep_ticking_the_same() {
+        let area = Rectangle::new(0, 0, 11, 11);
+        let food = FoodConf { count: 2, ..FoodConf::default() };
+        let mut game = Game::new(area, SpawnConf::default(), food, HazardConf::default(), Vec::new(), GameMode::Zen(Zen), 3)
+            .unwrap();
+        for _ in 0..10 {
+            steer(&mut game);
+            game.tick();
+        }
+
+        let save = SaveState { game: game.clone(), replay: Some(Replay::new(&game)), timer_accumulator: 0.125 };
+        let mut content = Vec::new();
+        save.write(&mut content).unwrap();
+        let mut resumed = SaveState::read(content.as_slice()).unwrap();
+        assert_eq!(resumed.timer_accumulator, 0.125);
+        assert!(resumed.replay.is_some());
+
+        for _ in 0..100 {
+            steer(&mut game);
+            steer(&mut resumed.game);
+            game.tick();
+            resumed.game.tick();
+            assert_eq!(serde_json::to_string(&resumed.game).unwrap(), serde_json::to_string(&game).unwrap());
+        }
+        assert!(game.food_eaten() > 2);
+    }
+
+    #[test]
+    fn saves_with_a_broken_body_are_refused() {
+        let area = Rectangle::new(0, 0, 11, 11);
+        let mut game = Game::new(area, SpawnConf::default(), FoodConf::default(), HazardConf::default(), Vec::new(), GameMode::default(), 3)
+            .unwrap();
+        game.player.body.truncate(1);
+        let save = SaveState { game, replay: None, timer_accumulator: 0.0 };
+        let mut content = Vec::new();
+        save.write(&mut content).unwrap();
+        assert_eq!(SaveState::read(content.as_slice()).err().map(|e| e.kind()), Some(io::ErrorKind::InvalidData));
+    }
+}
@@ -0,0 +1,251 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use image::{imageops, ImageResult, Rgba, RgbaImage};
+
+use crate::game::Game;
+use crate::hazard::Hazard;
+use crate::player::{SpriteType, BodyError};
+use crate::replay::Replay;
+use crate::utils::{Vec2D, quarter_turns};
+use crate::utils::constants::*;
+
+pub struct SoftwareRenderer {
+    head_sprite: RgbaImage,
+    body_sprite: RgbaImage,
+    body_right_sprite: RgbaImage,
+    body_left_sprite: RgbaImage,
+    body_sprite_big: RgbaImage,
+    body_right_sprite_big: RgbaImage,
+    body_left_sprite_big: RgbaImage,
+    tail_sprite: RgbaImage,
+    food_sprite: RgbaImage,
+}
+
+impl SoftwareRenderer {
+    pub fn load(resources_dir: &Path) -> ImageResult<SoftwareRenderer> {
+        let load = |name: &str| image::open(resources_dir.join(name)).map(|image| image.to_rgba());
+
+        Ok(SoftwareRenderer {
+            head_sprite: load("snake_head.png")?,
+            body_sprite: load("snake_body.png")?,
+            body_right_sprite: load("snake_body_right.png")?,
+            body_left_sprite: load("snake_body_left.png")?,
+            body_sprite_big: load("snake_body_big.png")?,
+            body_right_sprite_big: load("snake_body_right_big.png")?,
+            body_left_sprite_big: load("snake_body_left_big.png")?,
+            tail_sprite: load("snake_tail.png")?,
+            food_sprite: load("food.png")?,
+        })
+    }
+
+    pub fn render(&self, game: &Game) -> RgbaImage {
+        let width = game.playing_area.width() * CELL_SIZE;
+        let height = game.playing_area.height() * CELL_SIZE;
+        let mut canvas = RgbaImage::from_pixel(width, height, Rgba([255, 255, 255, 255]));
+
+        for (i, (first, second)) in game.board.portals().iter().enumerate() {
+            let (r, g, b) = PORTAL_COLORS[i % PORTAL_COLORS.len()];
+            fill_cell(&mut canvas, first, Rgba([r, g, b, 255]));
+            fill_cell(&mut canvas, second, Rgba([r, g, b, 255]));
+        }
+
+        for hazard in game.hazards.iter() {
+            let color = match hazard {
+                Hazard::Bouncer { .. } => Rgba([200, 40, 40, 255]),
+                Hazard::TemporaryWall { is_up: true, .. } => Rgba([70, 70, 70, 255]),
+                Hazard::TemporaryWall { is_up: false, .. } => continue,
+            };
+            fill_cell(&mut canvas, &hazard.position(), color);
+        }
+
+        for food in game.food.iter() {
+            draw_sprite(&mut canvas, &self.food_sprite, &food.position);
+        }
+
+        // Broken segments are left out, `export_replay` reports them.
+        for body_part in game.player.body.iter() {
+            let (sprite_type, rotation) = match body_part.get_sprite_and_rotation() {
+                Ok(sprite_and_rotation) => sprite_and_rotation,
+                Err(_) => continue,
+            };
+            let sprite = match (sprite_type, body_part.is_big) {
+                (SpriteType::Head, _) => &self.head_sprite,
+                (SpriteType::Tail, _) => &self.tail_sprite,
+                (SpriteType::Straight, true) => &self.body_sprite_big,
+                (SpriteType::Straight, false) => &self.body_sprite,
+                (SpriteType::Left, true) => &self.body_left_sprite_big,
+                (SpriteType::Left, false) => &self.body_left_sprite,
+                (SpriteType::Right, true) => &self.body_right_sprite_big,
+                (SpriteType::Right, false) => &self.body_right_sprite,
+            };
+            draw_sprite(&mut canvas, &rotate(sprite, rotation), &body_part.position);
+        }
+
+        canvas
+    }
+}
+
+// Sprites are centered on their cell, like the ggez renderer does with a 0.5 offset.
+fn draw_sprite(canvas: &mut RgbaImage, sprite: &RgbaImage, position: &Vec2D) {
+    let x = position.x * CELL_SIZE as i32 + (CELL_SIZE as i32 - sprite.width() as i32) / 2;
+    let y = position.y * CELL_SIZE as i32 + (CELL_SIZE as i32 - sprite.height() as i32) / 2;
+    imageops::overlay(canvas, sprite, x.max(0) as u32, y.max(0) as u32);
+}
+
+fn fill_cell(canvas: &mut RgbaImage, position: &Vec2D, color: Rgba<u8>) {
+    for y in 0..CELL_SIZE {
+        for x in 0..CELL_SIZE {
+            canvas.put_pixel(position.x as u32 * CELL_SIZE + x, position.y as u32 * CELL_SIZE + y, color);
+        }
+    }
+}
+
+fn rotate(sprite: &RgbaImage, rotation: f32) -> RgbaImage {
+    match quarter_turns(rotation) {
+        1 => imageops::rotate90(sprite),
+        2 => imageops::rotate180(sprite),
+        3 => imageops::rotate270(sprite),
+        _ => sprite.clone(),
+    }
+}
+
+pub fn resources_dir() -> PathBuf {
+    if let Ok(manifest_dir) = std::env::var("CARGO_MANIFEST_DIR") {
+        return PathBuf::from(manifest_dir).join("resources");
+    }
+
+    let mut path = std::env::current_exe().unwrap_or_default();
+    path.pop();
+    path.join("resources")
+}
+
+pub trait FrameWriter {
+    fn write_frame(&mut self, frame: &RgbaImage) -> io::Result<()>;
+}
+
+pub struct PngSequenceWriter {
+    directory: PathBuf,
+    frame_index: usize,
+}
+
+impl PngSequenceWriter {
+    pub fn new(directory: &Path) -> io::Result<PngSequenceWriter> {
+        fs::create_dir_all(directory)?;
+        Ok(PngSequenceWriter {
+            directory: directory.to_path_buf(),
+            frame_index: 0,
+        })
+    }
+}
+
+impl FrameWriter for PngSequenceWriter {
+    fn write_frame(&mut self, frame: &RgbaImage) -> io::Result<()> {
+        let path = self.directory.join(format!("frame_{:05}.png", self.frame_index));
+        self.frame_index += 1;
+        frame.save(path)
+    }
+}
+
+pub struct GifWriter<W: Write> {
+    encoder: gif::Encoder<W>,
+    delay: u16,
+}
+
+impl<W: Write> GifWriter<W> {
+    pub fn new(writer: W, width: u32, height: u32, frame_delay: f32) -> io::Result<GifWriter<W>> {
+        use gif::SetParameter;
+
+        let mut encoder = gif::Encoder::new(writer, width as u16, height as u16, &[])?;
+        encoder.set(gif::Repeat::Infinite)?;
+        Ok(GifWriter {
+            encoder,
+            delay: (frame_delay * 100.0).round() as u16,
+        })
+    }
+}
+
+impl<W: Write> FrameWriter for GifWriter<W> {
+    fn write_frame(&mut self, frame: &RgbaImage) -> io::Result<()> {
+        let mut pixels = frame.clone().into_raw();
+        let mut gif_frame = gif::Frame::from_rgba(frame.width() as u16, frame.height() as u16, &mut pixels);
+        gif_frame.delay = self.delay;
+        self.encoder.write_frame(&gif_frame)
+    }
+}
+
+// Exports as an animated GIF when `output` ends with `.gif`, as a directory of PNG frames otherwise.
+pub fn export_replay(replay_path: &Path, output: &Path) -> io::Result<()> {
+    let replay = Replay::read(File::open(replay_path)?)?;
+    let renderer = SoftwareRenderer::load(&resources_dir())
+        .map_err(|e| io::Error::other(e.to_string()))?;
+
+    let mut writer: Box<dyn FrameWriter> = match output.extension() {
+        Some(extension) if extension == "gif" => Box::new(GifWriter::new(
+            File::create(output)?,
+            replay.width * CELL_SIZE,
+            replay.height * CELL_SIZE,
+            MOVE_DELAY
+        )?),
+        _ => Box::new(PngSequenceWriter::new(output)?),
+    };
+
+    let mut result = Ok(());
+    let mut body_error: Option<BodyError> = None;
+    replay.play(|game| {
+        if result.is_ok() {
+            // Logged when it first shows up rather than for every frame it lasts.
+            let error = game.player.validate_body(&game.board).err();
+            if let Some(e) = error.filter(|_| error != body_error) {
+                log::warn!("Invalid snake body at tick {}: {}", game.ticks(), e);
+            }
+            body_error = error;
+            result = writer.write_frame(&renderer.render(game));
+        }
+    })?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::food::FoodConf;
+    use crate::hazard::HazardConf;
+    use crate::mode::GameMode;
+    use crate::spawn::SpawnConf;
+    use crate::utils::{Direction, Rectangle};
+
+    // Set SNAKE_UPDATE_GOLDEN=1 to write the expected image again after a deliberate change.
+    const GOLDEN_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/turns.png");
+
+    // A snake that ate once and turned both ways, so every sprite type shows up, in several rotations.
+    fn turning_game() -> Game {
+        let area = Rectangle::new(0, 0, 9, 9);
+        let mut game = Game::new(
+            area, SpawnConf::default(), FoodConf::default(), HazardConf::default(), Vec::new(), GameMode::default(), 1
+        ).unwrap();
+        let head = game.player.body[0].position;
+        assert!(game.place_food(head + game.player.direction.to_vec()));
+
+        let inputs = [None, Some(Direction::Up), None, Some(Direction::Left), None, Some(Direction::Down)];
+        for input in inputs.iter() {
+            game.player.wanted_direction = *input;
+            game.tick();
+        }
+        game
+    }
+
+    #[test]
+    fn render_matches_golden_image() {
+        let renderer = SoftwareRenderer::load(&resources_dir()).unwrap();
+        let frame = renderer.render(&turning_game());
+
+        if std::env::var("SNAKE_UPDATE_GOLDEN").is_ok() {
+            frame.save(GOLDEN_PATH).unwrap();
+        }
+        let golden = image::open(GOLDEN_PATH).unwrap().to_rgba();
+        assert_eq!(frame.dimensions(), golden.dimensions());
+        assert!(frame.pixels().eq(golden.pixels()), "Rendered frame differs from {}", GOLDEN_PATH);
+    }
+}
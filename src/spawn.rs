@@ -0,0 +1,240 @@
+use std::fmt;
+
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+
+use crate::board::Board;
+use crate::utils::{Vec2D, Rectangle, Direction};
+use crate::utils::constants::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Placement {
+    Centered,
+    Random,
+}
+
+impl Placement {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Placement::Centered => "centered",
+            Placement::Random => "random",
+        }
+    }
+
+    pub fn by_name(name: &str) -> Option<Placement> {
+        match name {
+            "centered" => Some(Placement::Centered),
+            "random" => Some(Placement::Random),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpawnConf {
+    pub length: usize,
+    pub placement: Placement,
+}
+
+impl Default for SpawnConf {
+    fn default() -> SpawnConf {
+        SpawnConf {
+            length: INITIAL_LENGTH,
+            placement: Placement::Centered,
+        }
+    }
+}
+
+// Where the head starts and which way the snake goes, the body trails straight behind the head.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spawn {
+    pub head: Vec2D,
+    pub direction: Direction,
+    pub length: usize,
+}
+
+impl Spawn {
+    pub fn new(head: Vec2D, direction: Direction, length: usize) -> Spawn {
+        Spawn {
+            head,
+            direction,
+            length,
+        }
+    }
+
+    // Cells of the snake, from the head to the tail.
+    pub fn cells(&self) -> impl Iterator<Item = Vec2D> {
+        let (head, behind) = (self.head, self.direction.opposite().to_vec());
+        (0..self.length as i32).map(move |i| head + behind * i)
+    }
+
+    // Cells right in front of the head that have to stay free so the player has time to react.
+    fn clearance(&self) -> impl Iterator<Item = Vec2D> {
+        let (head, ahead) = (self.head, self.direction.to_vec());
+        (1..=SPAWN_CLEARANCE as i32).map(move |i| head + ahead * i)
+    }
+
+    fn fits(&self, board: &Board) -> bool {
+        self.cells().chain(self.clearance()).all(|cell| board.is_free(&cell))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpawnError {
+    InvalidLength(usize),
+    BoardTooSmall { width: u32, height: u32, required: u32 },
+    NoRoom,
+}
+
+impl fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpawnError::InvalidLength(length) => {
+                write!(f, "The snake needs at least {} segments, got {}", MIN_LENGTH, length)
+            }
+            SpawnError::BoardTooSmall { width, height, required } => write!(
+                f,
+                "The board is {}x{}, it needs to be at least {} cells wide or tall to spawn the snake",
+                width, height, required
+            ),
+            SpawnError::NoRoom => write!(f, "There is no room left on the board to spawn the snake"),
+        }
+    }
+}
+
+impl std::error::Error for SpawnError {}
+
+// The snake lies in a straight line with some free cells ahead, so one side of the board must fit it.
+pub fn check_fits(area: &Rectangle, conf: &SpawnConf) -> Result<(), SpawnError> {
+    if conf.length < MIN_LENGTH {
+        return Err(SpawnError::InvalidLength(conf.length));
+    }
+
+    let required = (conf.length + SPAWN_CLEARANCE) as u32;
+    if area.width() < required && area.height() < required {
+        return Err(SpawnError::BoardTooSmall {
+            width: area.width(),
+            height: area.height(),
+            required,
+        });
+    }
+    Ok(())
+}
+
+pub fn find_spawn<R: Rng>(board: &Board, conf: &SpawnConf, rng: &mut R) -> Result<Spawn, SpawnError> {
+    check_fits(board.area(), conf)?;
+
+    match conf.placement {
+        Placement::Centered => centered_spawn(board.area(), conf.length)
+            .filter(|spawn| spawn.fits(board))
+            .ok_or(SpawnError::NoRoom),
+        Placement::Random => {
+            let candidates: Vec<Spawn> = board.area().cells()
+                .flat_map(|head| Direction::ALL.iter().map(move |&direction| Spawn::new(head, direction, conf.length)))
+                .filter(|spawn| spawn.fits(board))
+                .collect();
+            if candidates.is_empty() {
+                return Err(SpawnError::NoRoom);
+            }
+            Ok(candidates[rng.gen_range(0, candidates.len())])
+        }
+    }
+}
+
+// The snake and its clearance are centered along the row in the middle of the board, going right,
+// or along the middle column going down when the board is too narrow.
+fn centered_spawn(area: &Rectangle, length: usize) -> Option<Spawn> {
+    let span = (length + SPAWN_CLEARANCE) as i32;
+    let center = Vec2D::new(area.min.x + area.width() as i32 / 2, area.min.y + area.height() as i32 / 2);
+
+    if area.width() as i32 >= span {
+        let tail_x = area.min.x + (area.width() as i32 - span) / 2;
+        Some(Spawn::new(Vec2D::new(tail_x + length as i32 - 1, center.y), Direction::Right, length))
+    } else if area.height() as i32 >= span {
+        let tail_y = area.min.y + (area.height() as i32 - span) / 2;
+        Some(Spawn::new(Vec2D::new(center.x, tail_y + length as i32 - 1), Direction::Down, length))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_pcg::Pcg32;
+
+    const LENGTH: usize = 5;
+    const SPAN: i32 = (LENGTH + SPAWN_CLEARANCE) as i32;
+
+    fn conf(placement: Placement) -> SpawnConf {
+        SpawnConf { length: LENGTH, placement }
+    }
+
+    fn spawn_on(width: i32, height: i32, placement: Placement, seed: u64) -> Result<Spawn, SpawnError> {
+        let board = Board::new(&Rectangle::new(0, 0, width - 1, height - 1));
+        find_spawn(&board, &conf(placement), &mut Pcg32::seed_from_u64(seed))
+    }
+
+    #[test]
+    fn centered_spawn_goes_along_the_middle_row() {
+        let spawn = spawn_on(SPAN, SPAN, Placement::Centered, 0).unwrap();
+        assert_eq!(spawn, Spawn::new(Vec2D::new(LENGTH as i32 - 1, SPAN / 2), Direction::Right, LENGTH));
+        // The clearance ends on the last column.
+        assert_eq!(spawn.clearance().last(), Some(Vec2D::new(SPAN - 1, SPAN / 2)));
+
+        let wider = spawn_on(SPAN + 2, 10, Placement::Centered, 0).unwrap();
+        assert_eq!(wider.head, Vec2D::new(LENGTH as i32, 5));
+    }
+
+    #[test]
+    fn centered_spawn_falls_back_to_the_middle_column() {
+        let spawn = spawn_on(SPAN - 1, SPAN, Placement::Centered, 0).unwrap();
+        assert_eq!(spawn, Spawn::new(Vec2D::new((SPAN - 1) / 2, LENGTH as i32 - 1), Direction::Down, LENGTH));
+    }
+
+    #[test]
+    fn boards_one_cell_too_small_are_refused() {
+        let expected = SpawnError::BoardTooSmall { width: SPAN as u32 - 1, height: SPAN as u32 - 1, required: SPAN as u32 };
+        assert_eq!(spawn_on(SPAN - 1, SPAN - 1, Placement::Centered, 0), Err(expected.clone()));
+        assert_eq!(spawn_on(SPAN - 1, SPAN - 1, Placement::Random, 0), Err(expected.clone()));
+        let area = Rectangle::new(0, 0, SPAN - 2, SPAN - 2);
+        assert_eq!(check_fits(&area, &conf(Placement::Centered)), Err(expected));
+        assert_eq!(check_fits(&Rectangle::new(0, 0, SPAN - 2, SPAN - 1), &conf(Placement::Centered)), Ok(()));
+    }
+
+    #[test]
+    fn short_snakes_are_refused() {
+        let area = Rectangle::new(0, 0, 19, 19);
+        let conf = SpawnConf { length: MIN_LENGTH - 1, placement: Placement::Centered };
+        assert_eq!(check_fits(&area, &conf), Err(SpawnError::InvalidLength(MIN_LENGTH - 1)));
+    }
+
+    #[test]
+    fn taken_cells_leave_no_room() {
+        let mut board = Board::new(&Rectangle::new(0, 0, SPAN - 1, SPAN - 1));
+        let spawn = find_spawn(&board, &conf(Placement::Centered), &mut Pcg32::seed_from_u64(0)).unwrap();
+        board.set(&spawn.clearance().last().unwrap(), crate::board::Cell::Wall);
+        assert_eq!(find_spawn(&board, &conf(Placement::Centered), &mut Pcg32::seed_from_u64(0)), Err(SpawnError::NoRoom));
+    }
+
+    #[test]
+    fn random_spawn_depends_only_on_the_seed() {
+        for seed in 0..20 {
+            let spawn = spawn_on(SPAN, SPAN, Placement::Random, seed).unwrap();
+            assert_eq!(spawn_on(SPAN, SPAN, Placement::Random, seed), Ok(spawn));
+            let board = Board::new(&Rectangle::new(0, 0, SPAN - 1, SPAN - 1));
+            assert!(spawn.fits(&board));
+        }
+        let spawns: Vec<Spawn> = (0..20).map(|seed| spawn_on(SPAN, SPAN, Placement::Random, seed).unwrap()).collect();
+        assert!(spawns.iter().any(|spawn| *spawn != spawns[0]));
+    }
+
+    #[test]
+    fn placement_names_round_trip() {
+        for placement in [Placement::Centered, Placement::Random].iter() {
+            assert_eq!(Placement::by_name(placement.name()), Some(*placement));
+        }
+        assert_eq!(Placement::by_name("middle"), None);
+    }
+}
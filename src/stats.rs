@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+use serde::{Serialize, Deserialize};
+
+use crate::mode::GameMode;
+use crate::player::DeathCause;
+use crate::utils::constants::MOVE_DELAY;
+
+// What the profile hears about from the game.
+pub enum StatsEvent {
+    // `length` counts the segments still to grow. A length reached stays a record even if the
+    // run is abandoned, everything else waits for the run to end.
+    Ate { length: usize },
+    Ended { mode: GameMode, score: u32, ticks: u64, food: u64, length: usize, cause: DeathCause },
+}
+
+// Lifetime statistics over every finished run.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Profile {
+    pub games_played: u32,
+    pub food_eaten: u64,
+    pub longest_snake: usize,
+    pub longest_run: u64,
+    pub total_ticks: u64,
+    // Keyed by `DeathCause::name`.
+    pub deaths: BTreeMap<String, u32>,
+    // Keyed by the game mode name.
+    pub best_scores: BTreeMap<String, u32>,
+}
+
+impl Profile {
+    // A missing file is a new player, not an error.
+    pub fn load(path: &Path) -> io::Result<Profile> {
+        match File::open(path) {
+            Ok(file) => Ok(serde_json::from_reader(file)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Profile::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        serde_json::to_writer_pretty(File::create(path)?, self)?;
+        Ok(())
+    }
+
+    pub fn record(&mut self, event: &StatsEvent) {
+        match *event {
+            StatsEvent::Ate { length } => {
+                self.longest_snake = self.longest_snake.max(length);
+            }
+            StatsEvent::Ended { mode, score, ticks, food, length, cause } => {
+                self.games_played += 1;
+                self.food_eaten += food;
+                self.longest_snake = self.longest_snake.max(length);
+                self.longest_run = self.longest_run.max(ticks);
+                self.total_ticks += ticks;
+                *self.deaths.entry(cause.name().to_string()).or_insert(0) += 1;
+                let best = self.best_scores.entry(mode.to_string()).or_insert(0);
+                *best = (*best).max(score);
+            }
+        }
+    }
+
+    // Ties go to the cause that comes first alphabetically.
+    pub fn most_common_death(&self) -> Option<(&str, u32)> {
+        self.deaths.iter()
+            .rev()
+            .max_by_key(|(_, &count)| count)
+            .map(|(name, &count)| (name.as_str(), count))
+    }
+
+    pub fn average_run(&self) -> Option<f32> {
+        if self.games_played == 0 {
+            None
+        } else {
+            Some(self.total_ticks as f32 / self.games_played as f32)
+        }
+    }
+
+    // Lines of the stats screen, durations in seconds at normal speed.
+    pub fn summary(&self) -> Vec<String> {
+        let seconds = |ticks: f32| ticks * MOVE_DELAY;
+        let mut lines = vec![
+            format!("Games played: {}", self.games_played),
+            format!("Food eaten: {}", self.food_eaten),
+            format!("Longest snake: {}", self.longest_snake),
+            format!("Longest run: {:.1}s", seconds(self.longest_run as f32)),
+        ];
+        if let Some(average) = self.average_run() {
+            lines.push(format!("Average run: {:.1}s", seconds(average)));
+        }
+        if let Some((cause, count)) = self.most_common_death() {
+            lines.push(format!("Most common death: {} ({})", cause, count));
+        }
+        for (mode, score) in self.best_scores.iter() {
+            lines.push(format!("Best in {}: {}", mode, score));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mode::{Classic, Zen};
+    use crate::utils::Vec2D;
+
+    fn ended(score: u32, ticks: u64, cause: DeathCause) -> StatsEvent {
+        StatsEvent::Ended { mode: GameMode::Classic(Classic), score, ticks, food: 2, length: 6, cause }
+    }
+
+    #[test]
+    fn records_finished_runs() {
+        let mut profile = Profile::default();
+        profile.record(&StatsEvent::Ate { length: 8 });
+        profile.record(&ended(3, 40, DeathCause::TimedOut));
+        profile.record(&ended(5, 20, DeathCause::HitWall(Vec2D::new(0, 0))));
+        profile.record(&StatsEvent::Ended {
+            mode: GameMode::Zen(Zen), score: 1, ticks: 10, food: 1, length: 9, cause: DeathCause::TimedOut,
+        });
+
+        assert_eq!(profile.games_played, 3);
+        assert_eq!(profile.food_eaten, 5);
+        assert_eq!(profile.longest_snake, 9);
+        assert_eq!(profile.longest_run, 40);
+        assert_eq!(profile.total_ticks, 70);
+        assert_eq!(profile.deaths.get("Out of time"), Some(&2));
+        assert_eq!(profile.deaths.get("Wall"), Some(&1));
+        assert_eq!(profile.best_scores.get("Classic"), Some(&5));
+        assert_eq!(profile.best_scores.get("Zen"), Some(&1));
+    }
+
+    #[test]
+    fn eating_alone_only_counts_the_length() {
+        let mut profile = Profile::default();
+        profile.record(&StatsEvent::Ate { length: 8 });
+        assert_eq!(profile.food_eaten, 0);
+        assert_eq!(profile.games_played, 0);
+        assert_eq!(profile.longest_snake, 8);
+    }
+
+    #[test]
+    fn most_common_death_breaks_ties_alphabetically() {
+        let mut profile = Profile::default();
+        assert_eq!(profile.most_common_death(), None);
+        profile.record(&ended(0, 1, DeathCause::TimedOut));
+        profile.record(&ended(0, 1, DeathCause::HitHazard(Vec2D::new(0, 0))));
+        profile.record(&ended(0, 1, DeathCause::HitWall(Vec2D::new(0, 0))));
+        assert_eq!(profile.most_common_death(), Some(("Hazard", 1)));
+        profile.record(&ended(0, 1, DeathCause::HitWall(Vec2D::new(1, 0))));
+        assert_eq!(profile.most_common_death(), Some(("Wall", 2)));
+    }
+
+    #[test]
+    fn average_run_needs_a_game() {
+        let mut profile = Profile::default();
+        assert_eq!(profile.average_run(), None);
+        profile.record(&ended(0, 10, DeathCause::TimedOut));
+        profile.record(&ended(0, 15, DeathCause::TimedOut));
+        assert_eq!(profile.average_run(), Some(12.5));
+    }
+
+    #[test]
+    fn summary_shows_seconds_at_normal_speed() {
+        let mut profile = Profile::default();
+        assert_eq!(profile.summary().len(), 4);
+        profile.record(&ended(3, 40, DeathCause::TimedOut));
+        assert_eq!(profile.summary(), vec![
+            "Games played: 1",
+            "Food eaten: 2",
+            "Longest snake: 6",
+            "Longest run: 10.0s",
+            "Average run: 10.0s",
+            "Most common death: Out of time (1)",
+            "Best in Classic: 3",
+        ]);
+    }
+}
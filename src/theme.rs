@@ -0,0 +1,149 @@
+use ggez::{graphics, Context, GameResult};
+
+use crate::utils::Rectangle;
+use crate::utils::constants::*;
+
+pub enum BoardPattern {
+    Plain,
+    Checkerboard(graphics::Color),
+    GridLines(graphics::Color),
+}
+
+pub enum Background {
+    Color(graphics::Color),
+    // The colour is used instead when the image cannot be loaded.
+    Image(String, graphics::Color),
+}
+
+pub struct Border {
+    pub color: graphics::Color,
+    pub width: f32,
+}
+
+pub struct Theme {
+    pub background: Background,
+    pub pattern: BoardPattern,
+    pub border: Option<Border>,
+    pub surround_color: graphics::Color,
+    // Space around the board, in cells, left free for the HUD.
+    pub margin: u32,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme {
+            background: Background::Color(graphics::WHITE),
+            pattern: BoardPattern::Plain,
+            border: None,
+            surround_color: graphics::Color::from_rgb(40, 40, 40),
+            margin: 0,
+        }
+    }
+}
+
+impl Theme {
+    pub fn grass() -> Theme {
+        Theme {
+            background: Background::Color(graphics::Color::from_rgb(170, 215, 81)),
+            pattern: BoardPattern::Checkerboard(graphics::Color::from_rgb(162, 209, 73)),
+            border: Some(Border {
+                color: graphics::Color::from_rgb(87, 138, 52),
+                width: 4.0,
+            }),
+            surround_color: graphics::Color::from_rgb(74, 117, 44),
+            margin: 1,
+        }
+    }
+
+    pub fn blueprint() -> Theme {
+        Theme {
+            background: Background::Color(graphics::Color::from_rgb(30, 60, 110)),
+            pattern: BoardPattern::GridLines(graphics::Color::from_rgb(70, 110, 170)),
+            border: Some(Border {
+                color: graphics::WHITE,
+                width: 2.0,
+            }),
+            surround_color: graphics::Color::from_rgb(20, 40, 80),
+            margin: 1,
+        }
+    }
+
+    pub fn by_name(name: &str) -> Option<Theme> {
+        match name {
+            "default" => Some(Theme::default()),
+            "grass" => Some(Theme::grass()),
+            "blueprint" => Some(Theme::blueprint()),
+            _ => None,
+        }
+    }
+
+    pub fn set_background_image(&mut self, path: String) {
+        self.background = Background::Image(path, self.background_color());
+    }
+
+    pub fn background_color(&self) -> graphics::Color {
+        match self.background {
+            Background::Color(color) | Background::Image(_, color) => color,
+        }
+    }
+
+    pub fn margin_size(&self) -> f32 {
+        (self.margin * CELL_SIZE) as f32
+    }
+
+    // Everything drawn under the snake, in board pixels. The background image, if any, is drawn separately,
+    // so there may be nothing left to build.
+    pub fn build_board_mesh(&self, ctx: &mut Context, playing_area: &Rectangle) -> GameResult<Option<graphics::Mesh>> {
+        if let (Background::Image(..), BoardPattern::Plain, None) = (&self.background, &self.pattern, &self.border) {
+            return Ok(None);
+        }
+
+        let width = playing_area.width();
+        let height = playing_area.height();
+        let board_rect = graphics::Rect::new(0.0, 0.0, (width * CELL_SIZE) as f32, (height * CELL_SIZE) as f32);
+
+        let mut builder = graphics::MeshBuilder::new();
+        if let Background::Color(color) = self.background {
+            builder.rectangle(graphics::DrawMode::fill(), board_rect, color);
+        }
+
+        match self.pattern {
+            BoardPattern::Plain => (),
+            BoardPattern::Checkerboard(color) => {
+                for y in 0..height {
+                    for x in (y % 2..width).step_by(2) {
+                        let cell_rect = graphics::Rect::new(
+                            (x * CELL_SIZE) as f32,
+                            (y * CELL_SIZE) as f32,
+                            CELL_SIZE as f32,
+                            CELL_SIZE as f32
+                        );
+                        builder.rectangle(graphics::DrawMode::fill(), cell_rect, color);
+                    }
+                }
+            }
+            BoardPattern::GridLines(color) => {
+                for x in 1..width {
+                    let x = (x * CELL_SIZE) as f32;
+                    builder.line(&[[x, 0.0], [x, board_rect.h]], GRID_LINE_WIDTH, color)?;
+                }
+                for y in 1..height {
+                    let y = (y * CELL_SIZE) as f32;
+                    builder.line(&[[0.0, y], [board_rect.w, y]], GRID_LINE_WIDTH, color)?;
+                }
+            }
+        }
+
+        if let Some(border) = &self.border {
+            let border_rect = graphics::Rect::new(
+                -border.width / 2.0,
+                -border.width / 2.0,
+                board_rect.w + border.width,
+                board_rect.h + border.width
+            );
+            builder.rectangle(graphics::DrawMode::stroke(border.width), border_rect, border.color);
+        }
+
+        builder.build(ctx).map(Some)
+    }
+}
@@ -0,0 +1,149 @@
+use crate::utils::constants::*;
+
+// Turns frame times into a whole number of simulation steps, so the game runs at the same pace
+// whatever the frame rate. Time is scaled by `speed` before being accumulated.
+#[derive(Debug, Clone)]
+pub struct FixedTimestep {
+    step: f32,
+    speed: f32,
+    accumulator: f32,
+}
+
+impl FixedTimestep {
+    pub fn new(step: f32) -> FixedTimestep {
+        FixedTimestep {
+            step,
+            speed: 1.0,
+            accumulator: 0.0,
+        }
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    // NaN would go through the clamp and stall the game on catch-up, so it is ignored.
+    pub fn set_speed(&mut self, speed: f32) {
+        if !speed.is_nan() {
+            self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+        }
+    }
+
+    // Time already accumulated toward the next step, in simulation seconds.
+    pub fn accumulator(&self) -> f32 {
+        self.accumulator
+    }
+
+    pub fn set_accumulator(&mut self, accumulator: f32) {
+        self.accumulator = accumulator.clamp(0.0, self.step);
+    }
+
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+    }
+
+    // After a long stall only `MAX_CATCH_UP_STEPS` steps are kept, the rest of the time is dropped
+    // instead of fast-forwarding through it.
+    pub fn advance(&mut self, dt: f32) {
+        self.accumulator = (self.accumulator + dt * self.speed).min(self.step * MAX_CATCH_UP_STEPS as f32);
+    }
+
+    // Consumes one step if enough time was accumulated, call it in a loop.
+    pub fn next_step(&mut self) -> bool {
+        if self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            true
+        } else {
+            false
+        }
+    }
+
+    // Real time left until the next step.
+    pub fn time_until_step(&self) -> f32 {
+        ((self.step - self.accumulator) / self.speed).max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn steps(timestep: &mut FixedTimestep) -> u32 {
+        let mut count = 0;
+        while timestep.next_step() {
+            count += 1;
+        }
+        count
+    }
+
+    #[test]
+    fn long_frames_run_several_steps() {
+        let mut timestep = FixedTimestep::new(0.25);
+        timestep.advance(0.125);
+        assert_eq!(steps(&mut timestep), 0);
+        timestep.advance(0.625);
+        assert_eq!(steps(&mut timestep), 3);
+        assert_eq!(timestep.accumulator(), 0.0);
+    }
+
+    #[test]
+    fn leftover_time_carries_over() {
+        let mut timestep = FixedTimestep::new(0.25);
+        timestep.advance(0.375);
+        assert_eq!(steps(&mut timestep), 1);
+        assert_eq!(timestep.accumulator(), 0.125);
+        timestep.advance(0.125);
+        assert_eq!(steps(&mut timestep), 1);
+    }
+
+    #[test]
+    fn stalls_only_catch_up_a_few_steps() {
+        let mut timestep = FixedTimestep::new(0.25);
+        timestep.advance(60.0);
+        assert_eq!(steps(&mut timestep), MAX_CATCH_UP_STEPS);
+        assert_eq!(timestep.accumulator(), 0.0);
+    }
+
+    #[test]
+    fn speed_scales_time_and_is_clamped() {
+        let mut timestep = FixedTimestep::new(0.25);
+        timestep.set_speed(2.0);
+        timestep.advance(0.25);
+        assert_eq!(steps(&mut timestep), 2);
+
+        timestep.set_speed(100.0);
+        assert_eq!(timestep.speed(), MAX_SPEED);
+        timestep.set_speed(0.0);
+        assert_eq!(timestep.speed(), MIN_SPEED);
+    }
+
+    #[test]
+    fn nan_speed_is_ignored() {
+        let mut timestep = FixedTimestep::new(0.25);
+        timestep.set_speed(2.0);
+        timestep.set_speed(f32::NAN);
+        assert_eq!(timestep.speed(), 2.0);
+        timestep.advance(0.125);
+        assert_eq!(timestep.accumulator(), 0.25);
+    }
+
+    #[test]
+    fn time_until_step_is_in_real_time() {
+        let mut timestep = FixedTimestep::new(0.25);
+        timestep.advance(0.125);
+        assert_eq!(timestep.time_until_step(), 0.125);
+        timestep.set_speed(2.0);
+        assert_eq!(timestep.time_until_step(), 0.0625);
+        timestep.set_speed(0.5);
+        assert_eq!(timestep.time_until_step(), 0.25);
+    }
+
+    #[test]
+    fn restored_accumulators_stay_within_a_step() {
+        let mut timestep = FixedTimestep::new(0.25);
+        timestep.set_accumulator(1.0);
+        assert_eq!(timestep.accumulator(), 0.25);
+        timestep.set_accumulator(-1.0);
+        assert_eq!(timestep.accumulator(), 0.0);
+    }
+}
@@ -0,0 +1,257 @@
+use std::fs::File;
+use std::io::{self, Stdout, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crossterm::{cursor, queue, style, terminal};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+
+use rand::Rng;
+
+use crate::crash;
+use crate::game::{Game, TickOutcome};
+use crate::hazard::Hazard;
+use crate::player::SpriteType;
+use crate::replay::Replay;
+use crate::timestep::FixedTimestep;
+use crate::utils::{Vec2D, Direction, quarter_turns};
+use crate::utils::constants::*;
+
+const HEAD_GLYPHS: [char; 4] = ['▲', '▶', '▼', '◀'];
+const TAIL_GLYPHS: [char; 4] = ['╹', '╺', '╻', '╸'];
+const STRAIGHT_GLYPHS: [char; 4] = ['│', '─', '│', '─'];
+const STRAIGHT_BIG_GLYPHS: [char; 4] = ['┃', '━', '┃', '━'];
+const LEFT_GLYPHS: [char; 4] = ['┘', '└', '┌', '┐'];
+const LEFT_BIG_GLYPHS: [char; 4] = ['┛', '┗', '┏', '┓'];
+const RIGHT_GLYPHS: [char; 4] = ['└', '┌', '┐', '┘'];
+const RIGHT_BIG_GLYPHS: [char; 4] = ['┗', '┏', '┓', '┛'];
+const FOOD_GLYPH: char = '●';
+const BOUNCER_GLYPH: char = '◆';
+const WALL_GLYPH: char = '█';
+const FATAL_CELL_GLYPH: char = '✖';
+
+// Restores the terminal even if the game loop bails out early.
+struct Terminal {
+    stdout: Stdout,
+}
+
+impl Terminal {
+    fn enter() -> io::Result<Terminal> {
+        let mut stdout = io::stdout();
+        terminal::enable_raw_mode()?;
+        crossterm::execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide, terminal::Clear(terminal::ClearType::All))?;
+        Ok(Terminal { stdout })
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = crossterm::execute!(self.stdout, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+enum Input {
+    Quit,
+    Restart,
+    Turn(Direction),
+    Faster,
+    Slower,
+}
+
+pub fn run(mut game: Game, speed: f32) -> io::Result<()> {
+    let mut terminal = Terminal::enter()?;
+    let mut death_cause = None;
+    let mut timestep = FixedTimestep::new(MOVE_DELAY);
+    timestep.set_speed(speed);
+    let mut last_frame = Instant::now();
+    crash::start_run(&game, Some(&Replay::new(&game)));
+
+    loop {
+        let time_left = match game.mode.rules().time_left(game.ticks()) {
+            Some(time_left) => format!(" - Time {}s", (time_left / timestep.speed()).ceil()),
+            None => String::new(),
+        };
+        let status = match &death_cause {
+            Some(cause) => format!(
+                "Game Over ({}): {} - Score {} - space: restart, q: quit",
+                game.mode, cause, game.score
+            ),
+            None => format!(
+                "Score {}{} - Speed {}x - WASD/arrows: move, +/-: speed, q: quit",
+                game.score,
+                time_left,
+                timestep.speed()
+            ),
+        };
+        let fatal_cell = death_cause.as_ref().and_then(|cause| game.fatal_cell(cause));
+        draw(&mut terminal.stdout, &game, fatal_cell, &status)?;
+
+        let timeout = Duration::from_secs_f32(timestep.time_until_step());
+        match read_input(timeout)? {
+            Some(Input::Quit) => return Ok(()),
+            Some(Input::Restart) if death_cause.is_some() => {
+                game.reset(rand::thread_rng().gen());
+                crash::start_run(&game, Some(&Replay::new(&game)));
+                death_cause = None;
+                timestep.reset();
+            }
+            Some(Input::Turn(direction)) => game.player.wanted_direction = Some(direction),
+            Some(Input::Faster) => timestep.set_speed(timestep.speed() * 2.0),
+            Some(Input::Slower) => timestep.set_speed(timestep.speed() / 2.0),
+            _ => (),
+        }
+
+        let now = Instant::now();
+        timestep.advance((now - last_frame).as_secs_f32());
+        last_frame = now;
+        while timestep.next_step() {
+            if death_cause.is_none() {
+                crash::record(game.player.wanted_direction);
+                if let TickOutcome::Died(cause) = game.tick() {
+                    death_cause = Some(cause);
+                }
+            }
+        }
+    }
+}
+
+pub fn watch(replay_path: &Path, speed: f32) -> io::Result<()> {
+    let replay = Replay::read(File::open(replay_path)?)?;
+    let mut terminal = Terminal::enter()?;
+    let mut speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+
+    let mut result = Ok(());
+    let mut is_stopped = false;
+    replay.play(|game| {
+        if result.is_err() || is_stopped {
+            return;
+        }
+        let status = format!("Replay - Length {} - Speed {}x - +/-: speed, q: quit", game.player.body.len(), speed);
+        result = draw(&mut terminal.stdout, game, None, &status);
+
+        let next_tick = Instant::now() + Duration::from_secs_f32(MOVE_DELAY / speed);
+        while result.is_ok() && !is_stopped && Instant::now() < next_tick {
+            match read_input(next_tick.saturating_duration_since(Instant::now())) {
+                Ok(Some(Input::Quit)) => is_stopped = true,
+                Ok(Some(Input::Faster)) => speed = (speed * 2.0).min(MAX_SPEED),
+                Ok(Some(Input::Slower)) => speed = (speed / 2.0).max(MIN_SPEED),
+                Ok(_) => (),
+                Err(e) => result = Err(e),
+            }
+        }
+    })?;
+    result?;
+
+    while !is_stopped {
+        if let Some(Input::Quit) = read_input(Duration::from_secs_f32(MOVE_DELAY))? {
+            is_stopped = true;
+        }
+    }
+    Ok(())
+}
+
+fn read_input(timeout: Duration) -> io::Result<Option<Input>> {
+    if !event::poll(timeout)? {
+        return Ok(None);
+    }
+
+    if let Event::Key(key) = event::read()? {
+        if key.kind == KeyEventKind::Release {
+            return Ok(None);
+        }
+        return Ok(match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => Some(Input::Quit),
+            KeyCode::Char(' ') => Some(Input::Restart),
+            KeyCode::Char('+') | KeyCode::Char('=') => Some(Input::Faster),
+            KeyCode::Char('-') => Some(Input::Slower),
+            KeyCode::Char('w') | KeyCode::Up => Some(Input::Turn(Direction::Up)),
+            KeyCode::Char('s') | KeyCode::Down => Some(Input::Turn(Direction::Down)),
+            KeyCode::Char('a') | KeyCode::Left => Some(Input::Turn(Direction::Left)),
+            KeyCode::Char('d') | KeyCode::Right => Some(Input::Turn(Direction::Right)),
+            _ => None,
+        });
+    }
+    Ok(None)
+}
+
+// Every cell is two characters wide to keep the board roughly square, the second one
+// continues the line when the segment connects to the cell on its right.
+fn glyph(sprite_type: SpriteType, turns: u8, is_big: bool) -> (char, bool) {
+    let turns = turns as usize;
+    match (sprite_type, is_big) {
+        (SpriteType::Head, _) => (HEAD_GLYPHS[turns], turns == 3),
+        (SpriteType::Tail, _) => (TAIL_GLYPHS[turns], turns == 1),
+        (SpriteType::Straight, false) => (STRAIGHT_GLYPHS[turns], turns % 2 == 1),
+        (SpriteType::Straight, true) => (STRAIGHT_BIG_GLYPHS[turns], turns % 2 == 1),
+        (SpriteType::Left, false) => (LEFT_GLYPHS[turns], turns == 1 || turns == 2),
+        (SpriteType::Left, true) => (LEFT_BIG_GLYPHS[turns], turns == 1 || turns == 2),
+        (SpriteType::Right, false) => (RIGHT_GLYPHS[turns], turns == 0 || turns == 1),
+        (SpriteType::Right, true) => (RIGHT_BIG_GLYPHS[turns], turns == 0 || turns == 1),
+    }
+}
+
+fn draw(out: &mut Stdout, game: &Game, fatal_cell: Option<Vec2D>, status: &str) -> io::Result<()> {
+    let area = &game.playing_area;
+    let width = area.width() as usize;
+    let height = area.height() as usize;
+    let mut rows = vec![vec![' '; width * 2]; height];
+
+    // Both ends of a portal pair show the same letter.
+    for (i, (first, second)) in game.board.portals().iter().enumerate() {
+        let glyph = (b'A' + (i % 26) as u8) as char;
+        for position in [first, second].iter() {
+            rows[(position.y - area.min.y) as usize][(position.x - area.min.x) as usize * 2] = glyph;
+        }
+    }
+
+    for hazard in game.hazards.iter() {
+        let glyph = match hazard {
+            Hazard::Bouncer { .. } => BOUNCER_GLYPH,
+            Hazard::TemporaryWall { is_up: true, .. } => WALL_GLYPH,
+            Hazard::TemporaryWall { is_up: false, .. } => continue,
+        };
+        let position = hazard.position();
+        rows[(position.y - area.min.y) as usize][(position.x - area.min.x) as usize * 2] = glyph;
+    }
+
+    for food in game.food.iter() {
+        let position = food.position;
+        rows[(position.y - area.min.y) as usize][(position.x - area.min.x) as usize * 2] = FOOD_GLYPH;
+    }
+
+    // Printing would garble the alternate screen, broken segments are just left out.
+    for body_part in game.player.body.iter() {
+        let (sprite_type, rotation) = match body_part.get_sprite_and_rotation() {
+            Ok(sprite_and_rotation) => sprite_and_rotation,
+            Err(_) => continue,
+        };
+        let (glyph, connects_right) = glyph(sprite_type, quarter_turns(rotation), body_part.is_big);
+        let row = &mut rows[(body_part.position.y - area.min.y) as usize];
+        let column = (body_part.position.x - area.min.x) as usize * 2;
+        row[column] = glyph;
+        if connects_right {
+            row[column + 1] = if body_part.is_big { '━' } else { '─' };
+        }
+    }
+
+    if let Some(position) = fatal_cell {
+        rows[(position.y - area.min.y) as usize][(position.x - area.min.x) as usize * 2] = FATAL_CELL_GLYPH;
+    }
+
+    let horizontal_border: String = std::iter::repeat_n('═', width * 2).collect();
+    queue!(out, cursor::MoveTo(0, 0), style::Print(format!("╔{}╗", horizontal_border)))?;
+    for (y, row) in rows.iter().enumerate() {
+        let line: String = row.iter().collect();
+        queue!(out, cursor::MoveTo(0, y as u16 + 1), style::Print(format!("║{}║", line)))?;
+    }
+    queue!(
+        out,
+        cursor::MoveTo(0, height as u16 + 1),
+        style::Print(format!("╚{}╝", horizontal_border)),
+        cursor::MoveTo(0, height as u16 + 2),
+        style::Print(status),
+        terminal::Clear(terminal::ClearType::UntilNewLine)
+    )?;
+    out.flush()
+}
@@ -1,10 +1,11 @@
 use ggez::mint::Point2;
+use serde::{Serialize, Deserialize};
 
-impl Into<Point2<f32>> for Vec2D {
-    fn into(self: Self) -> Point2<f32> {
+impl From<Vec2D> for Point2<f32> {
+    fn from(v: Vec2D) -> Self {
         Point2::<f32> {
-            x: self.x as f32,
-            y: self.y as f32,
+            x: v.x as f32,
+            y: v.y as f32,
         }
     }
 }
@@ -29,7 +30,43 @@ impl std::ops::Add<Vec2D> for &Vec2D {
     }
 }
 
-#[derive(Debug, Hash, Eq, PartialEq, Clone, Copy)]
+impl std::ops::Sub<Vec2D> for Vec2D {
+    type Output = Vec2D;
+    fn sub(self, rhs: Vec2D) -> Self::Output {
+        Vec2D {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+        }
+    }
+}
+
+impl std::ops::Neg for Vec2D {
+    type Output = Vec2D;
+    fn neg(self) -> Self::Output {
+        Vec2D {
+            x: -self.x,
+            y: -self.y,
+        }
+    }
+}
+
+impl std::ops::Mul<i32> for Vec2D {
+    type Output = Vec2D;
+    fn mul(self, rhs: i32) -> Self::Output {
+        Vec2D {
+            x: self.x * rhs,
+            y: self.y * rhs,
+        }
+    }
+}
+
+impl From<(i32, i32)> for Vec2D {
+    fn from(p: (i32, i32)) -> Self {
+        Vec2D::new(p.0, p.1)
+    }
+}
+
+#[derive(Debug, Hash, Eq, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub struct Vec2D {
     pub x: i32,
     pub y: i32,
@@ -40,12 +77,18 @@ impl Vec2D {
         Vec2D {x, y}
     }
 
-    pub fn default() -> Vec2D {
+    pub fn manhattan_distance(&self, other: &Vec2D) -> u32 {
+        (self.x - other.x).unsigned_abs() + (self.y - other.y).unsigned_abs()
+    }
+}
+
+impl Default for Vec2D {
+    fn default() -> Vec2D {
         Vec2D::new(0, 0)
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub enum Direction {
     Up,
     Down,
@@ -53,6 +96,49 @@ pub enum Direction {
     Right
 }
 
+impl Direction {
+    // Clockwise, starting from Up, like sprite rotations.
+    pub const ALL: [Direction; 4] = [Direction::Up, Direction::Right, Direction::Down, Direction::Left];
+
+    // Offset of one step in this direction, y grows downwards.
+    pub fn to_vec(self) -> Vec2D {
+        match self {
+            Direction::Up => Vec2D::new(0, -1),
+            Direction::Down => Vec2D::new(0, 1),
+            Direction::Left => Vec2D::new(-1, 0),
+            Direction::Right => Vec2D::new(1, 0),
+        }
+    }
+
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+
+    pub fn rotate_left(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Left => Direction::Down,
+            Direction::Down => Direction::Right,
+            Direction::Right => Direction::Up,
+        }
+    }
+
+    pub fn rotate_right(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Rectangle {
     pub min: Vec2D,
     pub max: Vec2D,
@@ -80,12 +166,175 @@ impl Rectangle {
     pub fn height(&self) -> u32 {
         (self.max.y - self.min.y) as u32 + 1
     }
+
+    // Brings a point back inside by wrapping around the edges, as on a torus.
+    pub fn wrap(&self, point: &Vec2D) -> Vec2D {
+        Vec2D::new(
+            self.min.x + (point.x - self.min.x).rem_euclid(self.width() as i32),
+            self.min.y + (point.y - self.min.y).rem_euclid(self.height() as i32)
+        )
+    }
+
+    // Every cell of the rectangle, row by row.
+    pub fn cells(&self) -> impl Iterator<Item = Vec2D> {
+        let (min, max) = (self.min, self.max);
+        (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| Vec2D::new(x, y)))
+    }
+}
+
+// Sprite rotations are always quarter turns (clockwise, as in ggez), returns how many of them in 0..4.
+pub fn quarter_turns(rotation: f32) -> u8 {
+    ((rotation / std::f32::consts::FRAC_PI_2).round() as i32).rem_euclid(4) as u8
 }
 
 pub mod constants {
     pub const CELL_SIZE: u32 = 32;
-    pub const BODY_SIZE: u32 = 22;
-    pub const BIG_BODY_SIZE: u32 = 26;
     pub const FOOD_SIZE: u32 = 20;
     pub const MOVE_DELAY: f32 = 0.25;
-}
\ No newline at end of file
+    pub const MIN_SPEED: f32 = 0.25;
+    pub const MAX_SPEED: f32 = 8.0;
+    pub const MAX_CATCH_UP_STEPS: u32 = 5;
+    pub const TIME_ATTACK_SECONDS: u32 = 60;
+    pub const SURVIVAL_GROW_EVERY: u32 = 10;
+    pub const COUNTDOWN_WARNING: f32 = 10.0;
+    pub const TAIL_CUT_PENALTY: u32 = 1;
+    pub const FOOD_MIN_DISTANCE: u32 = 4;
+    pub const FOOD_EDGE_MARGIN: u32 = 1;
+    pub const BOUNCE_EVERY: u32 = 2;
+    pub const WALL_UP_TICKS: u32 = 20;
+    pub const WALL_DOWN_TICKS: u32 = 20;
+    pub const HAZARD_SPAWN_DISTANCE: u32 = 5;
+    pub const HAZARD_INSET: f32 = 4.0;
+    pub const PORTAL_COLORS: [(u8, u8, u8); 4] = [(130, 60, 210), (30, 150, 220), (240, 140, 20), (220, 50, 160)];
+    pub const MIN_LENGTH: usize = 2;
+    pub const INITIAL_LENGTH: usize = 7;
+    pub const SPAWN_CLEARANCE: usize = 2;
+    pub const LAST_REPLAY_PATH: &str = "/last_game.replay";
+    pub const SAVE_PATH: &str = "/save.json";
+    // Relative to the ggez user config directory, which is written to directly.
+    pub const STATS_FILE: &str = "stats.json";
+    pub const UNLOCKED_ACHIEVEMENTS_FILE: &str = "unlocked_achievements.json";
+    pub const ACHIEVEMENTS_PATH: &str = "/achievements.json";
+    // Names ggez derives the user directories from.
+    pub const GAME_ID: &str = "snake";
+    pub const GAME_AUTHOR: &str = "Corendos";
+    pub const GAME_OVER_FONT_SIZE: f32 = 32.0;
+    pub const DEATH_CAUSE_FONT_SIZE: f32 = 16.0;
+    pub const GAME_OVER_PADDING: f32 = 8.0;
+    pub const DEBUG_FONT_SIZE: f32 = 16.0;
+    pub const DEBUG_PADDING: f32 = 6.0;
+    pub const DEBUG_ARROW_WIDTH: f32 = 2.0;
+    pub const GRID_LINE_WIDTH: f32 = 1.0;
+    pub const PARTICLE_SIZE: u32 = 4;
+    pub const PARTICLE_SPEED: f32 = 120.0;
+    pub const PARTICLE_LIFETIME: f32 = 0.6;
+    pub const FOOD_PARTICLE_COUNT: usize = 16;
+    pub const DEATH_PARTICLE_COUNT: usize = 6;
+    pub const DEATH_ANIMATION_DURATION: f32 = 1.0;
+    pub const DEATH_FLASH_DURATION: f32 = 0.3;
+    pub const DEATH_FLASH_FREQUENCY: f32 = 20.0;
+    pub const EAT_SHAKE_INTENSITY: f32 = 2.0;
+    pub const EAT_SHAKE_DURATION: f32 = 0.15;
+    pub const DEATH_SHAKE_INTENSITY: f32 = 8.0;
+    pub const DEATH_SHAKE_DURATION: f32 = 0.5;
+    pub const TOAST_DURATION: f32 = 3.0;
+    pub const TOAST_FADE_DURATION: f32 = 0.5;
+    pub const TOAST_FONT_SIZE: f32 = 16.0;
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Small enough to check every combination.
+    fn sample_points() -> impl Iterator<Item = Vec2D> {
+        (-4..=4).flat_map(|x| (-4..=4).map(move |y| Vec2D::new(x, y)))
+    }
+
+    #[test]
+    fn opposite_is_an_involution() {
+        for direction in Direction::ALL.iter() {
+            assert_eq!(direction.opposite().opposite(), *direction);
+            assert_ne!(direction.opposite(), *direction);
+            assert_eq!(direction.opposite().to_vec(), -direction.to_vec());
+        }
+    }
+
+    #[test]
+    fn rotations_undo_each_other() {
+        // `ALL` is clockwise, so rotating right walks it forward.
+        for (i, direction) in Direction::ALL.iter().enumerate() {
+            assert_eq!(direction.rotate_right(), Direction::ALL[(i + 1) % 4]);
+            assert_eq!(direction.rotate_left().rotate_right(), *direction);
+            assert_eq!(direction.rotate_right().rotate_left(), *direction);
+            assert_eq!(direction.rotate_left().rotate_left(), direction.opposite());
+        }
+    }
+
+    #[test]
+    fn arithmetic_identities() {
+        for a in sample_points() {
+            assert_eq!(a - a, Vec2D::default());
+            assert_eq!(-(-a), a);
+            assert_eq!(a * 1, a);
+            assert_eq!(a * -1, -a);
+            assert_eq!(Vec2D::from((a.x, a.y)), a);
+            for b in sample_points() {
+                assert_eq!(a + b - b, a);
+                assert_eq!(a - b, a + -b);
+                assert_eq!(a + b, b + a);
+                assert_eq!((a + b) * 3, a * 3 + b * 3);
+            }
+        }
+    }
+
+    #[test]
+    fn manhattan_distance_is_a_metric() {
+        for a in sample_points() {
+            assert_eq!(a.manhattan_distance(&a), 0);
+            for b in sample_points() {
+                assert_eq!(a.manhattan_distance(&b), b.manhattan_distance(&a));
+                assert_eq!(a.manhattan_distance(&b) == 0, a == b);
+                let origin = Vec2D::default();
+                assert!(a.manhattan_distance(&b) <= a.manhattan_distance(&origin) + origin.manhattan_distance(&b));
+            }
+        }
+        for direction in Direction::ALL.iter() {
+            assert_eq!(Vec2D::default().manhattan_distance(&direction.to_vec()), 1);
+        }
+    }
+
+    #[test]
+    fn rectangle_cells_cover_every_cell_once() {
+        for (x_min, y_min, x_max, y_max) in [(0, 0, 0, 0), (0, 0, 9, 9), (-3, 2, 4, 2), (5, -5, 7, 10)].iter() {
+            let rectangle = Rectangle::new(*x_min, *y_min, *x_max, *y_max);
+            let cells: Vec<Vec2D> = rectangle.cells().collect();
+            assert_eq!(cells.len(), (rectangle.width() * rectangle.height()) as usize);
+            assert!(cells.iter().all(|cell| rectangle.contains(cell)));
+            let unique: std::collections::HashSet<&Vec2D> = cells.iter().collect();
+            assert_eq!(unique.len(), cells.len());
+        }
+    }
+
+    #[test]
+    fn wrap_brings_points_back_inside() {
+        let rectangle = Rectangle::new(2, 3, 6, 5);
+        for point in (-10..=15).flat_map(|x| (-10..=15).map(move |y| Vec2D::new(x, y))) {
+            let wrapped = rectangle.wrap(&point);
+            assert!(rectangle.contains(&wrapped));
+            assert_eq!((wrapped.x - point.x).rem_euclid(rectangle.width() as i32), 0);
+            assert_eq!((wrapped.y - point.y).rem_euclid(rectangle.height() as i32), 0);
+            if rectangle.contains(&point) {
+                assert_eq!(wrapped, point);
+            }
+        }
+    }
+
+    #[test]
+    fn quarter_turns_match_sprite_rotations() {
+        use std::f32::consts::{FRAC_PI_2, PI};
+        assert_eq!(quarter_turns(0.0), 0);
+        assert_eq!(quarter_turns(FRAC_PI_2), 1);
+        assert_eq!(quarter_turns(PI), 2);
+        assert_eq!(quarter_turns(-FRAC_PI_2), 3);
+    }
+}